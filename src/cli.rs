@@ -1,59 +1,1408 @@
 
-use crate::git::{gather_git_repo, get_branch_info, get_multi_directory_status, get_repo_state, print_branch_table, print_repo_table};
-use crate::primitives::{FuError};
-use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use crate::config::{Config, ConfigTimeout};
+use crate::display::{
+    check_date_format, finish_output, render_branch_null, render_dir_csv, render_dir_json, render_dir_metrics,
+    render_dir_null, render_dir_prometheus, should_colorize, wrap_escapes, write_line, ColorEnv, DateStyle, RepoStatusJson, TimeZoneMode,
+    DEFAULT_DATE_FORMAT,
+};
+use crate::git::{
+    branch_matches, fetch_directory, gather_git_repo, get_branch_info, get_current_branch, get_default_branch,
+    get_multi_directory_status, get_remote_info, get_repo_state, get_repos_status, get_tag_info, get_worktrees,
+    log_history, print_branch_table, print_repo_table, print_tag_table, prune_merged_branches, resolve_base,
+    AgeThresholds, BranchTableOptions, ColorBy, RepoColumn, RepoSort, RepoTableOptions, render_log,
+    render_remote_table, render_status_table, render_worktree_table, retain_needing_attention, retain_stale,
+};
+use crate::primitives::{BranchInfo, DirtyAttention, FetchBackend, FuError, HostFilter, RepoStatus, StatusOptions};
+use crate::template::render_template;
+use crate::theme::{Symbols, Theme};
+use crate::trace::{env_level, Level};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use std::collections::{BTreeMap, HashMap};
+use chrono::Local;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
 #[derive(Parser)]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Command,
+    /// Repo or scan root; `~` is expanded, and for dir-status `*`/`?` may select several roots
     #[arg(short = 'd', long, default_value = ".")]
     pub repo_path: PathBuf,
+    /// dir-status roots matched by a glob in --repo-path
+    #[arg(skip)]
+    pub scan_roots: Vec<PathBuf>,
+    /// Config file with flag defaults and a [theme] (default $XDG_CONFIG_HOME/r-git-fu/config.toml)
+    #[arg(long)]
+    pub config: Option<PathBuf>,
     #[arg(short, long, default_value = "false")]
     pub fetch: bool,
-    #[arg(short, long, default_value = "2500")]
-    pub timeout: u64,
+    /// Per-fetch timeout, e.g. "2s" or "500ms"; a bare number is milliseconds
+    #[arg(short, long, default_value = "2500", value_parser = parse_timeout)]
+    pub timeout: Duration,
+    /// How --fetch reaches remotes; cli falls back to libgit2 when git isn't installed
+    #[arg(long, value_enum, default_value = "cli")]
+    pub fetch_backend: FetchBackend,
+    /// With --fetch, skip repos whose last fetch (FETCH_HEAD mtime) is younger than this, e.g. "10m"
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub fetch_interval: Option<Duration>,
+    /// Report directories dir-status skipped because they couldn't be read, and fetches that
+    /// failed or timed out
+    #[arg(short, long, default_value = "false")]
+    pub verbose: bool,
+    /// Log every repo opened, fetch run and git error to stderr; R_GIT_FU_LOG=info|debug also works
+    #[arg(long, default_value = "false")]
+    pub debug: bool,
+    /// dir-status only: add an Age column with the time since each repo's HEAD commit
+    #[arg(long, default_value = "false")]
+    pub age: bool,
+    /// dir-status only: tabulate just the repos needing attention and count the rest in a footer
+    #[arg(long, default_value = "false")]
+    pub hide_clean: bool,
+    /// dir-status only: leave out the `N repos, D dirty, ...` totals line under the table
+    #[arg(long, default_value = "false")]
+    pub no_summary: bool,
+    /// dir-status only: redraw the table every interval, e.g. "30s", until Ctrl-C
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub watch: Option<Duration>,
+    /// with --watch: desktop notification when a repo turns dirty or gains unpushed commits
+    #[cfg(feature = "notify")]
+    #[arg(long, default_value = "false", requires = "watch")]
+    pub notify: bool,
     #[arg(long, short, default_value = "false")]
     pub remote_status: bool,
     #[arg(long, short, default_value = "false")]
     pub plain_tables: bool,
+    #[arg(long, default_value = "false")]
+    pub activity: bool,
+    #[arg(long, default_value = "14")]
+    pub activity_days: u32,
+    #[arg(long, default_value = "false")]
+    pub strip_ansi: bool,
+    /// Shorthand for --color never
+    #[arg(long, default_value = "false")]
+    pub no_color: bool,
+    /// When to emit colour escapes; auto honours NO_COLOR, CLICOLOR_FORCE and CLICOLOR, then colours
+    /// only terminals (or --shell prompts)
+    #[arg(long, value_enum, default_value = "auto")]
+    pub color: ColorChoice,
+    #[arg(long, default_value = "false")]
+    pub branch_stashes: bool,
+    /// Don't count submodule changes as dirty
+    #[arg(long, default_value = "false")]
+    pub ignore_submodules: bool,
+    /// Only count changes matching this pathspec, e.g. 'services/api/*' (repeatable)
+    #[arg(long = "pathspec")]
+    pub pathspecs: Vec<String>,
+    /// Append the upstream branch the ahead/behind counts are relative to
+    #[arg(long, default_value = "false")]
+    pub show_upstream: bool,
+    #[arg(long, value_enum, default_value = "human")]
+    pub format: OutputFormat,
+    /// dir-status/branches: NUL-terminated, tab-separated records instead of the table, for xargs -0 and read -d ''
+    #[arg(short = 'z', long, default_value = "false")]
+    pub null: bool,
+    /// Dirty categories that count as needing attention
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "all")]
+    pub dirty_attention: Vec<DirtyCategory>,
+    /// Only scan repos whose origin is on one of these hosts (repeatable)
+    #[arg(long = "allowed-host")]
+    pub allowed_hosts: Vec<String>,
+    /// Skip repos whose origin is on one of these hosts (repeatable)
+    #[arg(long = "denied-host")]
+    pub denied_hosts: Vec<String>,
+    /// Remote to compare against (defaults to the branch's upstream remote, then origin)
+    #[arg(long)]
+    pub remote: Option<String>,
+    /// Report ahead/behind against every remote that has the current branch
+    #[arg(long, default_value = "false")]
+    pub all_remotes: bool,
+    /// Also report commits not yet on the branch's push remote (branch.<name>.pushRemote or
+    /// remote.pushDefault) when that differs from the remote compared against
+    #[arg(long, default_value = "false")]
+    pub push_status: bool,
+    /// Only list branches matching this glob (e.g. "feature/*") or substring
+    #[arg(long)]
+    pub filter: Option<String>,
+    /// Only list the N most recent branches or log entries (0 means no limit)
+    #[arg(long)]
+    pub limit: Option<usize>,
+    /// Add Ahead/Behind columns comparing every branch with its upstream
+    #[arg(long, default_value = "false")]
+    pub with_position: bool,
+    /// branches: add Ahead of base/Behind base columns against this branch (bare --vs uses
+    /// origin/HEAD's branch)
+    #[arg(long, num_args = 0..=1)]
+    pub vs: Option<Option<String>>,
+    /// branches: only list branches whose last commit is older than this, oldest first
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub stale: Option<Duration>,
+    /// strftime layout for commit dates in the branches, tags and log tables
+    #[arg(long, default_value = DEFAULT_DATE_FORMAT, value_parser = parse_date_format)]
+    pub date_format: String,
+    /// Show commit dates in the local timezone rather than UTC
+    #[arg(long, default_value = "false")]
+    pub local_time: bool,
+    /// Show commit dates in the timezone each commit was made in
+    #[arg(long, default_value = "false", conflicts_with = "local_time")]
+    pub commit_tz: bool,
+    /// branches (and dir-status --color-by age): commits younger than this show green
+    #[arg(long, default_value = "1week", value_parser = humantime::parse_duration)]
+    pub recent_age: Duration,
+    /// branches (and dir-status --color-by age): commits older than this show red
+    #[arg(long, default_value = "30days", value_parser = humantime::parse_duration)]
+    pub stale_age: Duration,
+    /// log: one plain `date oid summary` line per commit instead of a table
+    #[arg(long, default_value = "false")]
+    pub oneline: bool,
+    /// Hide the Author column in the branches table
+    #[arg(long, default_value = "false")]
+    pub no_author: bool,
+    /// How many directory levels dir-status descends looking for repos
+    #[arg(long, default_value = "1")]
+    pub depth: usize,
+    /// Only list repos with local changes, divergence or an operation in progress
+    #[arg(long, default_value = "false")]
+    pub dirty_only: bool,
+    /// Prompt layout, e.g. "{branch}{ahead}{behind} {dirty}"; tokens: branch, ahead, behind,
+    /// dirty, stash, operation, oid
+    #[arg(long)]
+    pub template: Option<String>,
+    /// Wrap prompt colour escapes for embedding in this shell's PS1/PROMPT
+    #[arg(long, value_enum, default_value = "none")]
+    pub shell: PromptShell,
+    /// Repos to scan concurrently in dir-status (defaults to the number of CPUs)
+    #[arg(short, long)]
+    pub jobs: Option<usize>,
+    /// status/dir-status: read repo paths from this file (- for stdin), one per line; blank lines
+    /// and # comments are ignored
+    #[arg(long)]
+    pub repos_from: Option<PathBuf>,
+    /// dir-status: scan only the repos matching this config group's path globs; relative globs
+    /// are resolved against --repo-path
+    #[arg(long)]
+    pub group: Option<String>,
+    /// `[groups]` from the config file
+    #[arg(skip)]
+    pub groups: BTreeMap<String, Vec<String>>,
+    /// prompt/status/dir-status: exit 1 when a repo has worktree or index changes, 2 when it is only
+    /// ahead/behind
+    #[arg(long, default_value = "false")]
+    pub exit_code: bool,
+    /// Plain text everywhere: implies --plain-tables and --no-color
+    #[arg(long, default_value = "false")]
+    pub plain: bool,
+    /// Marker glyphs for prompts and tables; nerd needs a Nerd Font
+    #[arg(long, value_enum, default_value = "unicode")]
+    pub icons: IconSet,
+    /// dir-status: the table columns to show, in order
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub columns: Option<Vec<RepoColumn>>,
+    /// dir-status: color repo names by their state, or by HEAD commit age against --recent-age/--stale-age
+    #[arg(long, value_enum, default_value = "state")]
+    pub color_by: ColorBy,
+    /// dir-status: row order; name ascends, the rest put the largest or most recent first
+    #[arg(long, value_enum, default_value = "name")]
+    pub sort: RepoSort,
+    /// dir-status: flip the --sort order
+    #[arg(long, default_value = "false")]
+    pub reverse: bool,
+    /// ASCII markers (^ v * ok) instead of Unicode glyphs; same as --icons ascii
+    #[arg(long, visible_alias = "no-icons", default_value = "false")]
+    pub ascii: bool,
+    #[arg(skip)]
+    pub theme: Theme,
+}
+
+impl Cli {
+    /// Repos listed after `status`/`dir-status`, treated as repos rather than scanned
+    pub fn explicit_paths(&self) -> &[PathBuf] {
+        match &self.command {
+            Command::DirStatus { paths } | Command::Status { paths } => paths,
+            _ => &[],
+        }
+    }
+
+    pub fn log_level(&self) -> Level {
+        if self.debug {
+            Level::Debug
+        } else if self.verbose {
+            Level::Info
+        } else {
+            env_level()
+        }
+    }
+
+    pub fn jobs(&self) -> usize {
+        self.jobs.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        })
+    }
+
+    pub fn attention(&self) -> DirtyAttention {
+        let categories = &self.dirty_attention;
+        let wants = |category| categories.contains(&category) || categories.contains(&DirtyCategory::All);
+        DirtyAttention {
+            worktree: wants(DirtyCategory::Worktree),
+            index: wants(DirtyCategory::Index),
+            untracked: wants(DirtyCategory::Untracked),
+        }
+    }
+
+    pub fn host_filter(&self) -> HostFilter {
+        HostFilter {
+            allowed: self.allowed_hosts.clone(),
+            denied: self.denied_hosts.clone(),
+        }
+    }
+
+    pub fn color_enabled(&self) -> bool {
+        let flag = match self.color {
+            _ if self.no_color => Some(false),
+            ColorChoice::Always => Some(true),
+            ColorChoice::Never => Some(false),
+            ColorChoice::Auto => None,
+        };
+        // prompts are captured by the shell, so stdout is never a terminal there
+        let is_terminal = std::io::stdout().is_terminal() || self.shell != PromptShell::None;
+        should_colorize(flag, &ColorEnv::from_env(), is_terminal)
+    }
+
+    pub fn status_options(&self) -> StatusOptions {
+        StatusOptions::new()
+            .fetch(self.fetch)
+            .remote_status(self.remote_status)
+            .timeout(self.timeout)
+            .fetch_interval(self.fetch_interval)
+            .remote(self.remote.clone())
+            .all_remotes(self.all_remotes)
+            .push_status(self.push_status)
+            .branch_stashes(self.branch_stashes)
+            .ignore_submodules(self.ignore_submodules)
+            .pathspecs(self.pathspecs.clone())
+            .upstream_name(self.show_upstream)
+            .fetch_backend(self.fetch_backend)
+    }
+
+    pub fn date_style(&self) -> DateStyle {
+        let zone = if self.commit_tz {
+            TimeZoneMode::Commit
+        } else if self.local_time {
+            TimeZoneMode::Local
+        } else {
+            TimeZoneMode::Utc
+        };
+        DateStyle { zone, format: self.date_format.clone() }
+    }
+
+    pub fn age_thresholds(&self) -> AgeThresholds {
+        AgeThresholds { recent: self.recent_age, stale: self.stale_age }
+    }
+
+    pub fn repo_table_options(&self) -> RepoTableOptions {
+        RepoTableOptions {
+            plain_tables: self.plain_tables,
+            strip_ansi: self.strip_ansi,
+            hide_clean: self.hide_clean,
+            summary: !self.no_summary,
+            fetched: self.remote_status,
+            age: self.age,
+            columns: self.columns.clone(),
+            color_by: self.color_by,
+            sort: self.sort,
+            reverse: self.reverse,
+            ages: self.age_thresholds(),
+        }
+    }
+
+    /// Fills in flags that weren't given on the command line from `--config`, or the default
+    /// config path
+    pub fn load_config(&mut self, matches: &ArgMatches) -> Result<(), FuError> {
+        let Some(path) = self.config.clone().or_else(Config::default_path) else {
+            return Ok(());
+        };
+        let config = if self.config.is_some() {
+            // an explicitly named config has to exist
+            let contents = std::fs::read_to_string(&path).map_err(|err| {
+                FuError::Custom(format!("cannot read config {}: {}", path.display(), err))
+            })?;
+            Config::from_toml(&contents)?
+        } else {
+            Config::load(&path)?
+        };
+        let unset = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+
+        if let Some(repo_path) = config.repo_path.filter(|_| unset("repo_path")) {
+            self.repo_path = repo_path;
+        }
+        if let Some(fetch) = config.fetch.filter(|_| unset("fetch")) {
+            self.fetch = fetch;
+        }
+        if let Some(timeout) = config.timeout.filter(|_| unset("timeout")) {
+            let value = match timeout {
+                ConfigTimeout::Millis(millis) => millis.to_string(),
+                ConfigTimeout::Human(human) => human,
+            };
+            self.timeout = parse_timeout(&value)
+                .map_err(|err| FuError::Custom(format!("invalid config: timeout: {}", err)))?;
+        }
+        if let Some(remote_status) = config.remote_status.filter(|_| unset("remote_status")) {
+            self.remote_status = remote_status;
+        }
+        if let Some(plain_tables) = config.plain_tables.filter(|_| unset("plain_tables")) {
+            self.plain_tables = plain_tables;
+        }
+        if let Some(remote) = config.remote.filter(|_| unset("remote")) {
+            self.remote = Some(remote);
+        }
+        if let Some(jobs) = config.jobs.filter(|_| unset("jobs")) {
+            self.jobs = Some(jobs);
+        }
+        if let Some(date_format) = config.date_format.filter(|_| unset("date_format")) {
+            self.date_format = parse_date_format(&date_format)
+                .map_err(|err| FuError::Custom(format!("invalid config: date_format: {}", err)))?;
+        }
+        self.groups = config.groups;
+        self.theme = config.theme;
+        Ok(())
+    }
+
+    /// The repos `--group` selects, sorted; `None` without `--group`
+    pub fn group_repos(&self) -> Result<Option<Vec<PathBuf>>, FuError> {
+        let Some(name) = &self.group else {
+            return Ok(None);
+        };
+        let Some(patterns) = self.groups.get(name) else {
+            let known: Vec<&str> = self.groups.keys().map(String::as_str).collect();
+            return Err(FuError::Custom(if known.is_empty() {
+                format!("unknown group {}: the config defines no [groups]", name)
+            } else {
+                format!("unknown group {}: expected one of {}", name, known.join(", "))
+            }));
+        };
+        let mut repos: Vec<PathBuf> = patterns
+            .iter()
+            .flat_map(|pattern| glob_dirs(&self.repo_path.join(expand_tilde(pattern))))
+            .filter(|dir| git2::Repository::open(dir).is_ok())
+            .collect();
+        repos.sort();
+        repos.dedup();
+        Ok(Some(repos))
+    }
+
+    /// Appends the repos listed in `--repos-from` to the command's explicit paths
+    pub fn load_repo_list(&mut self) -> Result<(), FuError> {
+        let Some(source) = &self.repos_from else {
+            return Ok(());
+        };
+        let listed = if source.as_os_str() == "-" {
+            parse_repo_list(io::stdin().lock(), "stdin")
+        } else {
+            let file = std::fs::File::open(source).map_err(|err| {
+                FuError::Custom(format!("cannot read repo list {}: {}", source.display(), err))
+            })?;
+            parse_repo_list(io::BufReader::new(file), &source.display().to_string())
+        };
+        match &mut self.command {
+            Command::DirStatus { paths } | Command::Status { paths } => paths.extend(listed),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Expands `~` in --repo-path and, for dir-status, a glob over the roots to scan.
+    /// Paths that exist are canonicalized so errors name them absolutely.
+    pub fn expand_repo_path(&mut self) -> Result<(), FuError> {
+        let Some(raw) = self.repo_path.to_str() else {
+            return Ok(());
+        };
+        let path = expand_tilde(raw);
+        if matches!(self.command, Command::DirStatus { .. }) && path.to_string_lossy().contains(['*', '?']) {
+            let roots = glob_dirs(&path);
+            let Some(first) = roots.first() else {
+                return Err(FuError::Custom(format!("no directories match {}", path.display())));
+            };
+            self.repo_path = first.clone();
+            self.scan_roots = roots;
+            return Ok(());
+        }
+        self.repo_path = path.canonicalize().unwrap_or(path);
+        Ok(())
+    }
+
+    /// Settles flags that imply others or override the config's theme
+    pub fn apply_plain(&mut self) {
+        if self.plain {
+            self.plain_tables = true;
+            self.no_color = true;
+        }
+        if self.ascii {
+            self.icons = IconSet::Ascii;
+        }
+        self.theme.symbols = match self.icons {
+            IconSet::Unicode => Symbols::UNICODE,
+            IconSet::Nerd => Symbols::NERD,
+            IconSet::Ascii => Symbols::ASCII,
+        };
+    }
+}
+
+fn parse_date_format(value: &str) -> Result<String, String> {
+    check_date_format(value)?;
+    Ok(value.to_string())
+}
+
+fn parse_timeout(value: &str) -> Result<Duration, String> {
+    let timeout = match value.parse::<u64>() {
+        Ok(millis) => Duration::from_millis(millis),
+        Err(_) => humantime::parse_duration(value).map_err(|err| err.to_string())?,
+    };
+    if timeout.is_zero() {
+        return Err("timeout must be greater than zero".to_string());
+    }
+    Ok(timeout)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Human,
+    Json,
+    /// Prometheus exposition format aggregates (dir-status only)
+    Metrics,
+    /// One row per repo (dir-status only)
+    Csv,
+    /// Per-repo gauges for a node_exporter textfile collector (dir-status only)
+    Prometheus,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum IconSet {
+    Unicode,
+    Nerd,
+    Ascii,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PromptShell {
+    Bash,
+    Zsh,
+    None,
+}
+
+impl PromptShell {
+    /// Zero-width markers the shell needs around non-printing escapes
+    pub fn escape_markers(&self) -> Option<(&'static str, &'static str)> {
+        match self {
+            PromptShell::Bash => Some(("\\[", "\\]")),
+            PromptShell::Zsh => Some(("%{", "%}")),
+            PromptShell::None => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DirtyCategory {
+    Worktree,
+    Index,
+    Untracked,
+    All,
 }
 
 #[derive(Subcommand)]
 pub enum Command {
     Prompt,
     Branches,
-    DirStatus,
+    DirStatus {
+        /// Repos to report on directly instead of scanning -d for them
+        paths: Vec<PathBuf>,
+    },
+    /// Detailed status of the repo at -d, or of each repo given
+    Status {
+        paths: Vec<PathBuf>,
+    },
+    /// Commits reachable from HEAD, newest first
+    Log,
+    /// Configured remotes, their URLs and the current branch's position against each
+    Remotes,
+    /// Tags with their commit date, newest first
+    Tags,
+    /// Status of every linked worktree of the repo
+    Worktrees,
+    /// Fetch every repo under -d without printing a status table
+    Fetch,
+    /// List local branches already merged into the default branch (--vs overrides it)
+    Prune {
+        /// Delete them rather than just listing them
+        #[arg(long, visible_alias = "force")]
+        yes: bool,
+    },
+    /// Print a shell completion script to stdout
+    #[command(hide = true)]
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
 }
 
+/// Generated from the same parser, so every flag and subcommand is covered
+pub fn write_completions(shell: Shell, out: &mut impl io::Write) {
+    clap_complete::generate(shell, &mut Cli::command(), "r-git-fu", out);
+}
 
-pub fn get_prompt(path: &PathBuf, remote_status: bool) -> Result<(), FuError> {
-    let repo_result = gather_git_repo(path);
-    if let Ok(repo) = repo_result {
-        Ok(println!("{}", get_repo_state(&repo, false, remote_status, 0)?))
-    } else {
-        Ok(())
+
+pub fn get_prompt(cli: &Cli) -> Result<RepoStatus, FuError> {
+    let repo = gather_git_repo(&cli.repo_path)?;
+    // the prompt never fetches; it has to come back instantly
+    let mut options = cli.status_options().fetch(false);
+    if let (OutputFormat::Human, Some(template)) = (cli.format, &cli.template) {
+        // templates have no submodule placeholder, so skip work they can't show
+        options = options
+            .include_stash(template.contains("{stash}"))
+            .include_submodules(false);
     }
+    let repo_state = get_repo_state(&repo, &options)?;
+    write_line(&mut io::stdout(), &render_prompt(&repo_state, cli)?)?;
+    Ok(repo_state)
 }
 
-pub fn dump_branches(path: &PathBuf, plain_tables: bool) -> Result<(), FuError> {
-    let repo_result = gather_git_repo(path);
-    if let Ok(repo) = repo_result {
-        let branch_info = get_branch_info(&repo)?;
-        if let Some(branch_summary) = branch_info {
-            print_branch_table(branch_summary, plain_tables)
+/// The prompt line for an already-gathered state, without the trailing newline
+pub fn render_prompt(repo_state: &RepoStatus, cli: &Cli) -> Result<String, FuError> {
+    match cli.format {
+        OutputFormat::Human => {
+            let attention = cli.attention();
+            let rendered = match &cli.template {
+                Some(template) => {
+                    finish_output(render_template(template, repo_state, &attention, &cli.theme), cli.strip_ansi)
+                }
+                None => {
+                    let mut buf = Vec::new();
+                    repo_state.write_summary_to(&mut buf, !cli.strip_ansi, &attention, &cli.theme)?;
+                    String::from_utf8_lossy(&buf).into_owned()
+                }
+            };
+            Ok(match cli.shell.escape_markers() {
+                Some((open, close)) => wrap_escapes(&rendered, open, close),
+                None => rendered,
+            })
         }
-        Ok(())
+        OutputFormat::Json => Ok(serde_json::to_string(&RepoStatusJson::from(repo_state))?),
+        OutputFormat::Metrics | OutputFormat::Csv | OutputFormat::Prometheus => Err(FuError::Custom(format!(
+            "--format {} is only supported by dir-status",
+            cli.format.to_possible_value().unwrap().get_name()
+        ))),
+    }
+}
+
+/// One repo path per line; blank lines and `#` comments are skipped, and lines that can't be
+/// used are reported on stderr rather than failing the whole list.
+pub fn parse_repo_list(reader: impl io::BufRead, source: &str) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for (index, line) in reader.lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                eprintln!("r-git-fu: {}:{}: {}", source, index + 1, err);
+                continue;
+            }
+        };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let path = expand_tilde(line);
+        if path.exists() {
+            paths.push(path);
+        } else {
+            eprintln!("r-git-fu: {}:{}: {} does not exist", source, index + 1, line);
+        }
+    }
+    paths
+}
+
+/// `~` and `~/...` use $HOME, `~user/...` that user's home from /etc/passwd
+fn expand_tilde(path: &str) -> PathBuf {
+    let Some(rest) = path.strip_prefix('~') else {
+        return PathBuf::from(path);
+    };
+    let (user, rest) = rest.split_once('/').unwrap_or((rest, ""));
+    let home = if user.is_empty() {
+        std::env::var_os("HOME").map(PathBuf::from)
     } else {
-        Ok(())
+        std::fs::read_to_string("/etc/passwd")
+            .ok()
+            .and_then(|passwd| passwd_home(&passwd, user))
+    };
+    match home {
+        Some(home) => home.join(rest),
+        None => PathBuf::from(path),
+    }
+}
+
+fn passwd_home(passwd: &str, user: &str) -> Option<PathBuf> {
+    passwd.lines().find_map(|line| {
+        let fields: Vec<&str> = line.split(':').collect();
+        (fields.len() >= 6 && fields[0] == user).then(|| PathBuf::from(fields[5]))
+    })
+}
+
+/// Directories matching a path whose components may hold `*`/`?` globs, sorted.
+/// Hidden entries only match a component that itself starts with `.`.
+fn glob_dirs(pattern: &Path) -> Vec<PathBuf> {
+    let mut matches = vec![PathBuf::new()];
+    for component in pattern.components() {
+        let component = component.as_os_str();
+        let Some(glob) = component.to_str().filter(|part| part.contains(['*', '?'])) else {
+            matches.iter_mut().for_each(|path| path.push(component));
+            continue;
+        };
+        let mut next = Vec::new();
+        for base in &matches {
+            let dir = if base.as_os_str().is_empty() { Path::new(".") } else { base.as_path() };
+            let Ok(entries) = std::fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let name = entry.file_name();
+                let Some(name) = name.to_str() else {
+                    continue;
+                };
+                if name.starts_with('.') && !glob.starts_with('.') {
+                    continue;
+                }
+                if branch_matches(name, glob) {
+                    next.push(base.join(name));
+                }
+            }
+        }
+        matches = next;
+    }
+    let mut dirs: Vec<PathBuf> = matches
+        .into_iter()
+        .filter(|path| path.is_dir())
+        .map(|path| path.canonicalize().unwrap_or(path))
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+pub fn show_status(cli: &Cli) -> Result<Vec<RepoStatus>, FuError> {
+    let paths = cli.explicit_paths();
+    if paths.is_empty() {
+        return Ok(vec![show_repo_status(cli, &cli.repo_path)?]);
+    }
+    let mut statuses = Vec::new();
+    for path in paths {
+        if paths.len() > 1 {
+            write_line(&mut io::stdout(), &path.display().to_string())?;
+        }
+        match show_repo_status(cli, path) {
+            Ok(status) => statuses.push(status),
+            // one bad path in a list is noted, not fatal
+            Err(err) if paths.len() > 1 => eprintln!("r-git-fu: skipping {}: {}", path.display(), err),
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(statuses)
+}
+
+fn show_repo_status(cli: &Cli, path: &PathBuf) -> Result<RepoStatus, FuError> {
+    let repo = gather_git_repo(path)?;
+    let repo_state = get_repo_state(&repo, &cli.status_options())?;
+    let rendered = render_status_table(&repo_state, cli.plain_tables, &cli.attention(), &cli.theme);
+    write_line(&mut io::stdout(), &finish_output(rendered, cli.strip_ansi))?;
+    Ok(repo_state)
+}
+
+pub fn dump_log(cli: &Cli) -> Result<(), FuError> {
+    let repo = gather_git_repo(&cli.repo_path)?;
+    let entries = log_history(&repo, cli.limit, &cli.date_style())?;
+    if !entries.is_empty() {
+        let rendered = render_log(&entries, cli.plain_tables, cli.oneline);
+        write_line(&mut io::stdout(), &finish_output(rendered, cli.strip_ansi))?;
+    }
+    Ok(())
+}
+
+/// `-z` branch records, cut to `--limit` (0 meaning all, as for the table)
+fn null_branch_records(cli: &Cli, mut branches: Vec<BranchInfo>) -> String {
+    if let Some(limit) = cli.limit.filter(|&n| n > 0) {
+        branches.truncate(limit);
+    }
+    render_branch_null(&branches)
+}
+
+pub fn dump_branches(cli: &Cli) -> Result<(), FuError> {
+    let repo = gather_git_repo(&cli.repo_path)?;
+    let base = match &cli.vs {
+        Some(Some(name)) => Some(resolve_base(&repo, name)?),
+        Some(None) => Some(resolve_base(&repo, &default_branch(&repo)?)?),
+        None => None,
+    };
+    let branch_info = get_branch_info(
+        &repo,
+        cli.activity.then_some(cli.activity_days),
+        cli.filter.as_deref(),
+        cli.with_position,
+        base,
+        &cli.date_style(),
+    )?;
+    if let Some(mut branch_summary) = branch_info {
+        if let Some(older_than) = cli.stale {
+            retain_stale(&mut branch_summary, older_than);
+            if branch_summary.is_empty() {
+                return Ok(());
+            }
+        }
+        if cli.null {
+            io::stdout().write_all(null_branch_records(cli, branch_summary).as_bytes())?;
+            return Ok(());
+        }
+        let options = BranchTableOptions {
+            plain_tables: cli.plain_tables,
+            strip_ansi: cli.strip_ansi,
+            show_author: !cli.no_author,
+            with_position: cli.with_position,
+            limit: cli.limit,
+            symbols: cli.theme.symbols,
+            ages: cli.age_thresholds(),
+        };
+        print_branch_table(&mut io::stdout(), branch_summary, get_current_branch(&repo).as_deref(), &options)?;
+    }
+    Ok(())
+}
+
+fn default_branch(repo: &git2::Repository) -> Result<String, FuError> {
+    get_default_branch(repo)?
+        .ok_or_else(|| FuError::Custom("cannot detect the default branch; pass --vs <branch>".to_string()))
+}
+
+pub fn fetch_repos(cli: &Cli) -> Result<(), FuError> {
+    let options = cli.status_options().fetch(true);
+    let reports = fetch_directory(&cli.repo_path, &options, &cli.host_filter(), cli.jobs(), cli.depth)?;
+    let mut names: Vec<&String> = reports.keys().collect();
+    names.sort();
+    for name in names {
+        write_line(&mut io::stdout(), &format!("{}: {}", name, reports[name].label()))?;
+    }
+    Ok(())
+}
+
+pub fn prune_branches(cli: &Cli, delete: bool) -> Result<(), FuError> {
+    let repo = gather_git_repo(&cli.repo_path)?;
+    let default = match &cli.vs {
+        Some(Some(name)) => name.clone(),
+        _ => default_branch(&repo)?,
+    };
+    let merged = prune_merged_branches(&repo, &default, delete)?;
+    if merged.is_empty() {
+        write_line(&mut io::stdout(), &format!("no branches merged into {}", default))?;
+        return Ok(());
+    }
+    let verb = if delete { "deleted" } else { "would delete" };
+    for name in &merged {
+        write_line(&mut io::stdout(), &format!("{} {}", verb, name))?;
+    }
+    if !delete {
+        write_line(&mut io::stdout(), "run with prune --yes to delete them")?;
+    }
+    Ok(())
+}
+
+pub fn list_remotes(cli: &Cli) -> Result<(), FuError> {
+    let repo = gather_git_repo(&cli.repo_path)?;
+    let remotes = get_remote_info(&repo)?;
+    if remotes.is_empty() {
+        write_line(&mut io::stdout(), "no remotes")?;
+        return Ok(());
+    }
+    let rendered = render_remote_table(&remotes, cli.plain_tables, &cli.theme.symbols);
+    write_line(&mut io::stdout(), &finish_output(rendered, cli.strip_ansi))?;
+    Ok(())
+}
+
+pub fn dump_tags(cli: &Cli) -> Result<(), FuError> {
+    let repo = gather_git_repo(&cli.repo_path)?;
+    let tags = get_tag_info(&repo, &cli.date_style())?;
+    if !tags.is_empty() {
+        print_tag_table(&mut io::stdout(), &tags, cli.plain_tables, cli.strip_ansi)?;
     }
+    Ok(())
 }
 
-pub fn dir_status(path: &PathBuf, fetch: bool, timeout_ms: u64, plain_tables: bool) -> Result<(), FuError> {
-    let full_results = get_multi_directory_status(path, fetch, timeout_ms)?;
-    print_repo_table(full_results, plain_tables);
+pub fn list_worktrees(cli: &Cli) -> Result<(), FuError> {
+    let repo = gather_git_repo(&cli.repo_path)?;
+    let worktrees = get_worktrees(&repo)?;
+    if worktrees.is_empty() {
+        write_line(&mut io::stdout(), "no linked worktrees")?;
+        return Ok(());
+    }
+    let rendered = render_worktree_table(&worktrees, cli.plain_tables, &cli.theme);
+    write_line(&mut io::stdout(), &finish_output(rendered, cli.strip_ansi))?;
     Ok(())
 }
 
+/// Returns the statuses it reported on, so `--exit-code` can be derived from them
+pub fn dir_status(cli: &Cli) -> Result<HashMap<String, RepoStatus>, FuError> {
+    match cli.watch {
+        Some(interval) => watch_dir_status(cli, interval),
+        None => dir_status_once(cli, &cli.status_options().remote_status(true)),
+    }
+}
+
+fn watch_dir_status(cli: &Cli, interval: Duration) -> Result<HashMap<String, RepoStatus>, FuError> {
+    let stop = Arc::new(AtomicBool::new(false));
+    let handler_stop = Arc::clone(&stop);
+    ctrlc::set_handler(move || handler_stop.store(true, Ordering::Relaxed))
+        .map_err(|err| FuError::Custom(format!("cannot install Ctrl-C handler: {}", err)))?;
+
+    // without an explicit --fetch-interval, fetch at most once per redraw interval's worth of age
+    let options = cli
+        .status_options()
+        .remote_status(true)
+        .fetch_interval(cli.fetch_interval.or(Some(interval)));
+    let mut results = HashMap::new();
+    #[cfg(feature = "notify")]
+    let mut scanned = HashMap::new();
+    write!(io::stdout().lock(), "\x1b[?25l")?;
+    let outcome = watch_loop(interval, None, &stop, || {
+        let mut stdout = io::stdout().lock();
+        write!(stdout, "\x1b[2J\x1b[H")?;
+        let header = format!(
+            "Every {}: {}\n",
+            humantime::format_duration(interval),
+            Local::now().format("%Y-%m-%d %H:%M:%S")
+        );
+        write_line(&mut stdout, &header)?;
+        drop(stdout);
+        #[cfg(feature = "notify")]
+        let reported = if cli.notify {
+            for (repo, alert) in rescan_alerts(cli, &options, &mut scanned)? {
+                crate::notify::send(&repo, &alert);
+            }
+            report_dir_status(cli, scanned.clone())?
+        } else {
+            dir_status_once(cli, &options)?
+        };
+        #[cfg(not(feature = "notify"))]
+        let reported = dir_status_once(cli, &options)?;
+        results = reported;
+        io::stdout().flush()?;
+        Ok(())
+    });
+    // put the cursor back even when the loop failed, but report the loop's error first
+    let mut stdout = io::stdout().lock();
+    let restored = write!(stdout, "\x1b[?25h").and_then(|()| stdout.flush());
+    outcome?;
+    restored?;
+    Ok(results)
+}
+
+/// Replaces `scanned` with a fresh scan and returns what changed since the last one. Compares
+/// the whole scan, so repos `--dirty-only` leaves out while clean still alert when they turn dirty.
+#[cfg(feature = "notify")]
+fn rescan_alerts(
+    cli: &Cli,
+    options: &StatusOptions,
+    scanned: &mut HashMap<String, RepoStatus>,
+) -> Result<Vec<(String, crate::notify::RepoAlert)>, FuError> {
+    let current = scan_dir_status(cli, options)?;
+    let alerts = crate::notify::repo_alerts(scanned, &current);
+    *scanned = current;
+    Ok(alerts)
+}
+
+/// Runs `tick` every `interval` until `stop` is set or `max_iterations` ticks have run;
+/// returns how many ran
+fn watch_loop(
+    interval: Duration,
+    max_iterations: Option<usize>,
+    stop: &AtomicBool,
+    mut tick: impl FnMut() -> Result<(), FuError>,
+) -> Result<usize, FuError> {
+    let keep_going = |iterations: usize| {
+        !stop.load(Ordering::Relaxed) && max_iterations.is_none_or(|max| iterations < max)
+    };
+    let mut iterations = 0;
+    while keep_going(iterations) {
+        tick()?;
+        iterations += 1;
+        // sleep in short slices so Ctrl-C doesn't wait out the whole interval
+        let deadline = Instant::now() + interval;
+        while keep_going(iterations) {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            thread::sleep(remaining.min(Duration::from_millis(100)));
+        }
+    }
+    Ok(iterations)
+}
+
+/// Scans each globbed root, keying repos by the root's name so equal names don't collide
+fn scan_roots_status(
+    roots: &[PathBuf],
+    options: &StatusOptions,
+    cli: &Cli,
+) -> Result<Option<HashMap<String, RepoStatus>>, FuError> {
+    let mut merged = HashMap::new();
+    for root in roots {
+        let prefix = root.file_name().map_or_else(|| root.display().to_string(), |name| name.to_string_lossy().into_owned());
+        let found = get_multi_directory_status(root, options, &cli.host_filter(), cli.jobs(), cli.depth)?;
+        for (name, status) in found.into_iter().flatten() {
+            merged.insert(format!("{}/{}", prefix, name), status);
+        }
+    }
+    Ok((!merged.is_empty()).then_some(merged))
+}
+
+/// Repos under --repo-path are named relative to it, like a plain scan; others by full path
+fn group_status(repos: &[PathBuf], options: &StatusOptions, cli: &Cli) -> Option<HashMap<String, RepoStatus>> {
+    let root = cli.repo_path.canonicalize().unwrap_or_else(|_| cli.repo_path.clone());
+    let results = get_repos_status(repos, options, &cli.host_filter(), cli.jobs())?;
+    let named = results.into_iter().map(|(path, status)| {
+        let name = match Path::new(&path).strip_prefix(&root) {
+            Ok(relative) => relative.display().to_string(),
+            Err(_) => path,
+        };
+        (name, status)
+    });
+    Some(named.collect())
+}
+
+fn dir_status_once(cli: &Cli, options: &StatusOptions) -> Result<HashMap<String, RepoStatus>, FuError> {
+    report_dir_status(cli, scan_dir_status(cli, options)?)
+}
+
+/// Every repo dir-status covers, before `--dirty-only` narrows them down
+fn scan_dir_status(cli: &Cli, options: &StatusOptions) -> Result<HashMap<String, RepoStatus>, FuError> {
+    let paths = cli.explicit_paths();
+    let results = if let Some(repos) = cli.group_repos()? {
+        group_status(&repos, options, cli)
+    } else if cli.scan_roots.len() > 1 {
+        scan_roots_status(&cli.scan_roots, options, cli)?
+    } else if paths.is_empty() {
+        get_multi_directory_status(&cli.repo_path, options, &cli.host_filter(), cli.jobs(), cli.depth)?
+    } else {
+        get_repos_status(paths, options, &cli.host_filter(), cli.jobs())
+    };
+    Ok(results.unwrap_or_default())
+}
+
+/// Applies `--dirty-only` and writes the chosen format; returns the repos it reported on
+fn report_dir_status(
+    cli: &Cli,
+    mut results: HashMap<String, RepoStatus>,
+) -> Result<HashMap<String, RepoStatus>, FuError> {
+    // scrapes want every repo, so the metric formats ignore --dirty-only
+    if cli.dirty_only && !matches!(cli.format, OutputFormat::Metrics | OutputFormat::Prometheus) {
+        retain_needing_attention(&mut results, &cli.attention());
+        if cli.format == OutputFormat::Human && !cli.null && results.is_empty() {
+            write_line(&mut io::stdout(), "all clean")?;
+            return Ok(results);
+        }
+    }
+    if cli.null {
+        io::stdout().write_all(render_dir_null(&results).as_bytes())?;
+        return Ok(results);
+    }
+    match cli.format {
+        OutputFormat::Human => {
+            print_repo_table(
+                &mut io::stdout(),
+                (!results.is_empty()).then_some(&results),
+                &cli.attention(),
+                &cli.theme,
+                &cli.repo_table_options(),
+            )?
+        }
+        OutputFormat::Json => write_line(&mut io::stdout(), &render_dir_json(&results)?)?,
+        OutputFormat::Metrics => io::stdout().write_all(render_dir_metrics(&results, cli.fetch).as_bytes())?,
+        OutputFormat::Csv => io::stdout().write_all(render_dir_csv(&results).as_bytes())?,
+        OutputFormat::Prometheus => io::stdout().write_all(render_dir_prometheus(&results).as_bytes())?,
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::render_repo_table;
+    use crate::primitives::{BranchState, DirtyState, Position};
+    use clap::FromArgMatches;
+
+    #[test]
+    fn test_non_repo_path_is_an_error() -> Result<(), FuError> {
+        let tmp = tempfile::tempdir()?;
+        let path = tmp.path().to_str().unwrap();
+
+        for command in ["prompt", "branches"] {
+            let cli = Cli::parse_from(["r-git-fu", "-d", path, command]);
+            let result = match cli.command {
+                Command::Prompt => get_prompt(&cli).map(|_| ()),
+                _ => dump_branches(&cli),
+            };
+            let err = result.expect_err("non-repo path should fail");
+            assert!(err.to_string().contains("No .git directory found"));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_prompt_for_known_state() -> Result<(), FuError> {
+        let mut status = RepoStatus::broken_state("unused".to_string());
+        status.branch = BranchState::Named("main".to_string());
+        status.head_oid = git2::Oid::from_str("0123456789abcdef0123456789abcdef01234567")?;
+        status.position = Some(Position { ahead: 1, behind: 2, diverged: true });
+
+        let cli = Cli::parse_from(["r-git-fu", "--strip-ansi", "prompt"]);
+        assert_eq!(render_prompt(&status, &cli)?, "(main⇅↑1 ↓2|✔)");
+
+        let cli = Cli::parse_from(["r-git-fu", "--template", "{branch}:{oid}", "--strip-ansi", "prompt"]);
+        assert_eq!(render_prompt(&status, &cli)?, "main:0123456");
+
+        let cli = Cli::parse_from(["r-git-fu", "--format", "csv", "prompt"]);
+        assert!(render_prompt(&status, &cli).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_watch_loop_stops_after_max_iterations() -> Result<(), FuError> {
+        let stop = AtomicBool::new(false);
+        let mut ticks = 0;
+        let ran = watch_loop(Duration::from_millis(1), Some(3), &stop, || {
+            ticks += 1;
+            Ok(())
+        })?;
+        assert_eq!((ran, ticks), (3, 3));
+
+        stop.store(true, Ordering::Relaxed);
+        assert_eq!(watch_loop(Duration::ZERO, Some(3), &stop, || Ok(()))?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_timeout_durations() {
+        let timeout = |value: &str| {
+            Cli::try_parse_from(["r-git-fu", "--timeout", value, "prompt"]).map(|cli| cli.timeout)
+        };
+        assert_eq!(timeout("2s").unwrap(), Duration::from_secs(2));
+        assert_eq!(timeout("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(timeout("2500").unwrap(), Duration::from_millis(2500));
+        assert_eq!(Cli::parse_from(["r-git-fu", "prompt"]).timeout, Duration::from_millis(2500));
+
+        let err = timeout("0s").unwrap_err().to_string();
+        assert!(err.contains("greater than zero"), "{}", err);
+        assert!(timeout("soon").is_err());
+    }
+
+    // parses like main does, so the config sees which flags were given
+    fn parse_with_config(args: &[&str]) -> Result<Cli, FuError> {
+        let clap_error = |err: clap::Error| FuError::Custom(err.to_string());
+        let matches = Cli::command().try_get_matches_from(args).map_err(clap_error)?;
+        let mut cli = Cli::from_arg_matches(&matches).map_err(clap_error)?;
+        cli.load_config(&matches)?;
+        Ok(cli)
+    }
+
+    #[test]
+    fn test_config_defaults_and_overrides() -> Result<(), FuError> {
+        let tmp = tempfile::tempdir()?;
+        let path = tmp.path().join("config.toml");
+        std::fs::write(&path, "fetch = true\ntimeout = \"5s\"\nremote = \"upstream\"\njobs = 2\n")?;
+        let config = path.to_str().unwrap();
+
+        let cli = parse_with_config(&["r-git-fu", "--config", config, "dir-status"])?;
+        assert!(cli.fetch);
+        assert_eq!(cli.timeout, Duration::from_secs(5));
+        assert_eq!(cli.remote.as_deref(), Some("upstream"));
+        assert_eq!(cli.jobs(), 2);
+        assert!(!cli.remote_status);
+
+        let cli = parse_with_config(&[
+            "r-git-fu", "--config", config, "--timeout", "1s", "--remote", "origin", "-j", "8", "dir-status",
+        ])?;
+        assert_eq!(cli.timeout, Duration::from_secs(1));
+        assert_eq!(cli.remote.as_deref(), Some("origin"));
+        assert_eq!(cli.jobs(), 8);
+        assert!(cli.fetch);
+
+        assert!(parse_with_config(&["r-git-fu", "--config", "/nonexistent/fu.toml", "prompt"]).is_err());
+
+        std::fs::write(&path, "date_format = \"%Y-%m-%d\"\n")?;
+        assert_eq!(parse_with_config(&["r-git-fu", "--config", config, "tags"])?.date_format, "%Y-%m-%d");
+        std::fs::write(&path, "date_format = \"%Y-%!\"\n")?;
+        assert!(parse_with_config(&["r-git-fu", "--config", config, "tags"]).is_err());
+        assert!(Cli::try_parse_from(["r-git-fu", "--date-format", "%Y-%!", "tags"]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_group_selects_repos() -> Result<(), FuError> {
+        let tmp = tempfile::tempdir()?;
+        for name in ["work-api", "work-web", "home-dotfiles"] {
+            git2::Repository::init(tmp.path().join(name))?;
+        }
+        std::fs::create_dir(tmp.path().join("work-notes"))?;
+        let path = tmp.path().join("config.toml");
+        std::fs::write(&path, "[groups]\nwork = [\"work-*\"]\npersonal = [\"home-*\"]\n")?;
+        let config = path.to_str().unwrap();
+        let root = tmp.path().to_str().unwrap();
+
+        let cli = parse_with_config(&["r-git-fu", "--config", config, "-d", root, "--group", "work", "dir-status"])?;
+        let repos = cli.group_repos()?.unwrap();
+        assert_eq!(repos.len(), 2);
+        let results = group_status(&repos, &cli.status_options(), &cli).unwrap();
+        let mut names: Vec<&str> = results.keys().map(String::as_str).collect();
+        names.sort();
+        assert_eq!(names, ["work-api", "work-web"]);
+
+        let cli = parse_with_config(&["r-git-fu", "--config", config, "-d", root, "--group", "oss", "dir-status"])?;
+        let err = cli.group_repos().unwrap_err().to_string();
+        assert!(err.contains("unknown group oss") && err.contains("personal, work"), "{}", err);
+
+        let cli = parse_with_config(&["r-git-fu", "--config", config, "dir-status"])?;
+        assert_eq!(cli.group_repos()?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repos_from_file() -> Result<(), FuError> {
+        let tmp = tempfile::tempdir()?;
+        let first = tmp.path().join("first");
+        let second = tmp.path().join("second");
+        git2::Repository::init(&first)?;
+        git2::Repository::init(&second)?;
+        let list = tmp.path().join("repos.txt");
+        std::fs::write(
+            &list,
+            format!("# fleet\n{}\n\n{}\n{}\n", first.display(), second.display(), tmp.path().join("missing").display()),
+        )?;
+
+        let mut cli = Cli::parse_from(["r-git-fu", "--repos-from", list.to_str().unwrap(), "dir-status"]);
+        cli.load_repo_list()?;
+        assert_eq!(cli.explicit_paths(), [first.clone(), second.clone()]);
+
+        let results = get_repos_status(cli.explicit_paths(), &cli.status_options(), &cli.host_filter(), cli.jobs());
+        assert_eq!(results.map(|results| results.len()), Some(2));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "notify")]
+    #[test]
+    fn test_notify_sees_repos_hidden_by_dirty_only() -> Result<(), FuError> {
+        use crate::notify::RepoAlert;
+
+        let tmp = tempfile::tempdir()?;
+        git2::Repository::init(tmp.path().join("app"))?;
+        let path = tmp.path().to_str().unwrap();
+        let cli = Cli::parse_from([
+            "r-git-fu", "-d", path, "--watch", "1s", "--notify", "--dirty-only", "--format", "json", "dir-status",
+        ]);
+        let options = cli.status_options();
+        let mut scanned = HashMap::new();
+
+        assert!(rescan_alerts(&cli, &options, &mut scanned)?.is_empty());
+        assert!(scanned.contains_key("app"));
+        assert!(report_dir_status(&cli, scanned.clone())?.is_empty());
+
+        std::fs::write(tmp.path().join("app").join("notes.txt"), "draft\n")?;
+        let alerts = rescan_alerts(&cli, &options, &mut scanned)?;
+        assert_eq!(alerts, [("app".to_string(), RepoAlert::BecameDirty)]);
+        assert!(report_dir_status(&cli, scanned.clone())?.contains_key("app"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_null_branch_records_limit() {
+        let branches = || {
+            ["main", "dev", "topic"]
+                .map(|name| BranchInfo {
+                    name: name.to_string(),
+                    commit_time: 0,
+                    iso_date: "2024-01-01".to_string(),
+                    delta: String::new(),
+                    activity: None,
+                    author_name: None,
+                    author_email: None,
+                    position: None,
+                    base: None,
+                })
+                .into()
+        };
+        let records = |limit: &str| {
+            let cli = Cli::parse_from(["r-git-fu", "-z", "--limit", limit, "branches"]);
+            null_branch_records(&cli, branches()).matches('\0').count()
+        };
+        assert_eq!(records("0"), 3);
+        assert_eq!(records("2"), 2);
+    }
+
+    #[test]
+    fn test_tilde_expansion() {
+        if let Some(home) = std::env::var_os("HOME") {
+            assert_eq!(expand_tilde("~"), PathBuf::from(&home));
+            assert_eq!(expand_tilde("~/code"), PathBuf::from(&home).join("code"));
+        }
+        assert_eq!(expand_tilde("code/~"), PathBuf::from("code/~"));
+        assert_eq!(expand_tilde("~nobody-here-at-all/x"), PathBuf::from("~nobody-here-at-all/x"));
+
+        let passwd = "root:x:0:0:root:/root:/bin/bash\nana:x:1000:1000::/home/ana:/bin/zsh\n";
+        assert_eq!(passwd_home(passwd, "ana"), Some(PathBuf::from("/home/ana")));
+        assert_eq!(passwd_home(passwd, "an"), None);
+    }
+
+    #[test]
+    fn test_repo_path_glob() -> Result<(), FuError> {
+        let tmp = tempfile::tempdir()?;
+        for root in ["proj-a", "proj-b", "other"] {
+            git2::Repository::init(tmp.path().join(root).join("app"))?;
+        }
+        let pattern = tmp.path().join("proj-*");
+
+        let mut cli = Cli::parse_from(["r-git-fu", "-d", pattern.to_str().unwrap(), "dir-status"]);
+        cli.expand_repo_path()?;
+        let root = tmp.path().canonicalize()?;
+        assert_eq!(cli.scan_roots, [root.join("proj-a"), root.join("proj-b")]);
+
+        let results = scan_roots_status(&cli.scan_roots, &cli.status_options(), &cli)?.unwrap();
+        let mut names: Vec<_> = results.keys().cloned().collect();
+        names.sort();
+        assert_eq!(names, ["proj-a/app", "proj-b/app"]);
+
+        // other commands take the path literally
+        let mut cli = Cli::parse_from(["r-git-fu", "-d", pattern.to_str().unwrap(), "status"]);
+        cli.expand_repo_path()?;
+        assert!(cli.scan_roots.is_empty());
+        assert_eq!(cli.repo_path, pattern);
 
+        let mut cli = Cli::parse_from(["r-git-fu", "-d", tmp.path().join("nope-*").to_str().unwrap(), "dir-status"]);
+        assert!(cli.expand_repo_path().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bash_completions() {
+        let mut script = Vec::new();
+        write_completions(Shell::Bash, &mut script);
+        let script = String::from_utf8(script).unwrap();
+        for name in ["dir-status", "branches", "prompt", "--hide-clean"] {
+            assert!(script.contains(name), "{} missing", name);
+        }
+    }
+
+    #[test]
+    fn test_plain_implies_plain_tables_and_no_color() {
+        let mut cli = Cli::parse_from(["r-git-fu", "--plain", "prompt"]);
+        cli.apply_plain();
+        assert!(cli.plain_tables);
+        assert!(cli.no_color);
+
+        let mut cli = Cli::parse_from(["r-git-fu", "prompt"]);
+        cli.apply_plain();
+        assert!(!cli.plain_tables);
+        assert!(!cli.no_color);
+    }
+
+    #[test]
+    fn test_ascii_markers() -> Result<(), FuError> {
+        let mut status = RepoStatus::broken_state("unused".to_string());
+        status.branch = BranchState::Named("main".to_string());
+        status.head_oid = git2::Oid::from_str("0123456789abcdef0123456789abcdef01234567")?;
+        status.position = Some(Position { ahead: 1, behind: 2, diverged: true });
+        status.dirty = DirtyState { worktree: 1, index: 1, untracked: 1, conflicts: 1, renamed: 1 };
+        status.stash = 2;
+        status.upstream = Some("origin/main".to_string());
+        status.error = None;
+
+        let mut cli = Cli::parse_from(["r-git-fu", "--ascii", "--strip-ansi", "prompt"]);
+        cli.apply_plain();
+        let prompt = render_prompt(&status, &cli)?;
+        assert!(prompt.is_ascii(), "{}", prompt);
+        assert!(prompt.contains("^1 v2"));
+
+        let results = HashMap::from([("repo".to_string(), status)]);
+        let table = render_repo_table(&results, &cli.attention(), &cli.theme, &cli.repo_table_options());
+        assert!(table.is_ascii(), "{}", table);
+
+        let mut unicode = Cli::parse_from(["r-git-fu", "--strip-ansi", "prompt"]);
+        unicode.apply_plain();
+        assert!(!render_prompt(&results["repo"], &unicode)?.is_ascii());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_columns_flag() {
+        let cli = Cli::parse_from(["r-git-fu", "--columns", "repo,stash,dirty", "dir-status"]);
+        assert_eq!(cli.columns, Some(vec![RepoColumn::Repo, RepoColumn::Stash, RepoColumn::Dirty]));
+
+        let err = Cli::try_parse_from(["r-git-fu", "--columns", "repo,colour", "dir-status"])
+            .err()
+            .expect("unknown column should be rejected");
+        assert!(err.to_string().contains("invalid value 'colour'"));
+    }
+
+    #[test]
+    fn test_nerd_icons() -> Result<(), FuError> {
+        let mut status = RepoStatus::broken_state("unused".to_string());
+        status.branch = BranchState::Named("main".to_string());
+        status.position = Some(Position { ahead: 1, behind: 2, diverged: true });
+        status.dirty = DirtyState { worktree: 1, index: 0, untracked: 0, conflicts: 0, renamed: 0 };
+        status.error = None;
+
+        let mut cli = Cli::parse_from(["r-git-fu", "--icons", "nerd", "--strip-ansi", "prompt"]);
+        cli.apply_plain();
+        assert_eq!(render_prompt(&status, &cli)?, "(\u{e0a0} main\u{f0ec}\u{f077}1 \u{f078}2|\u{f111}1)");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_dirty_attention_categories() {
+        let cli = Cli::parse_from(["r-git-fu", "--dirty-attention", "index,untracked", "prompt"]);
+        let attention = cli.attention();
+        assert!(!attention.worktree);
+        assert!(attention.index);
+        assert!(attention.untracked);
+
+        let cli = Cli::parse_from(["r-git-fu", "prompt"]);
+        let attention = cli.attention();
+        assert!(attention.worktree && attention.index && attention.untracked);
+    }
+
+    #[test]
+    fn test_color_choice() {
+        let cli = Cli::parse_from(["r-git-fu", "--color", "never", "prompt"]);
+        assert!(!cli.color_enabled());
+
+        let cli = Cli::parse_from(["r-git-fu", "--color", "always", "prompt"]);
+        assert!(cli.color_enabled());
+
+        let cli = Cli::parse_from(["r-git-fu", "--color", "always", "--no-color", "prompt"]);
+        assert!(!cli.color_enabled());
+    }
+}
@@ -0,0 +1,96 @@
+use crate::primitives::FuError;
+use crate::theme::Theme;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Top-level keys are defaults for the matching flags; flags given on the command line win
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub repo_path: Option<PathBuf>,
+    pub fetch: Option<bool>,
+    pub timeout: Option<ConfigTimeout>,
+    pub remote_status: Option<bool>,
+    pub plain_tables: Option<bool>,
+    pub remote: Option<String>,
+    pub jobs: Option<usize>,
+    pub date_format: Option<String>,
+    /// `[groups]` tables: `work = ["~/work/*", "clients/*"]`, for `dir-status --group`
+    pub groups: BTreeMap<String, Vec<String>>,
+    pub theme: Theme,
+}
+
+/// `timeout = 2500` (milliseconds) or `timeout = "2s"`, like `--timeout`
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ConfigTimeout {
+    Millis(u64),
+    Human(String),
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/r-git-fu/config.toml`, falling back to `~/.config`
+    pub fn default_path() -> Option<PathBuf> {
+        let base = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(base.join("r-git-fu").join("config.toml"))
+    }
+
+    pub fn from_toml(contents: &str) -> Result<Self, FuError> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    /// A missing config file is not an error, it just means built-in defaults
+    pub fn load(path: &Path) -> Result<Self, FuError> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Self::from_toml(&contents),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme::ThemeColor;
+
+    #[test]
+    fn test_flag_defaults() -> Result<(), FuError> {
+        let config =
+            Config::from_toml("fetch = true\ntimeout = \"2s\"\njobs = 3\n[theme]\nbranch = \"blue\"\n")?;
+        assert_eq!(config.fetch, Some(true));
+        assert_eq!(config.timeout, Some(ConfigTimeout::Human("2s".to_string())));
+        assert_eq!(config.jobs, Some(3));
+        assert_eq!(config.remote_status, None);
+
+        let config = Config::from_toml("timeout = 500\n")?;
+        assert_eq!(config.timeout, Some(ConfigTimeout::Millis(500)));
+        assert!(Config::from_toml("fetchh = true\n").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_theme_section() -> Result<(), FuError> {
+        let config = Config::from_toml("[theme]\nbranch = \"blue\"\ndirty = \"magenta\"\n")?;
+        assert_eq!(config.theme.branch, ThemeColor::Blue);
+        assert_eq!(config.theme.dirty, ThemeColor::Magenta);
+        assert_eq!(config.theme.clean, Theme::default().clean);
+
+        assert!(Config::from_toml("[theme]\nbranch = \"mauve\"\n").is_err());
+
+        let config = Config::from_toml("[groups]\nwork = [\"~/work/*\", \"acme-*\"]\noss = []\n")?;
+        assert_eq!(config.groups["work"], ["~/work/*", "acme-*"]);
+        assert!(config.groups["oss"].is_empty());
+
+        let tmp = tempfile::tempdir()?;
+        let missing = Config::load(&tmp.path().join("config.toml"))?;
+        assert_eq!(missing.theme, Theme::default());
+
+        Ok(())
+    }
+}
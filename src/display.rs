@@ -1,8 +1,236 @@
-use crate::primitives::FuError;
-use chrono::{DateTime, TimeZone, Utc};
+use crate::primitives::{BranchInfo, BranchState, FetchOutcome, FuError, RepoStatus};
+use chrono::{DateTime, FixedOffset, Local, TimeZone, Utc};
 use comfy_table::modifiers::UTF8_ROUND_CORNERS;
 use comfy_table::presets::{ASCII_BORDERS_ONLY_CONDENSED, NOTHING};
 use comfy_table::Table;
+use owo_colors::{OwoColorize, Style};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static COLOR_ENABLED: AtomicBool = AtomicBool::new(true);
+
+pub fn set_color_enabled(enabled: bool) {
+    COLOR_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn color_enabled() -> bool {
+    COLOR_ENABLED.load(Ordering::Relaxed)
+}
+
+/// The variables that bear on colour, read once so the decision itself stays testable
+#[derive(Debug, Default, Clone)]
+pub struct ColorEnv {
+    pub no_color: Option<OsString>,
+    pub clicolor: Option<OsString>,
+    pub clicolor_force: Option<OsString>,
+}
+
+impl ColorEnv {
+    pub fn from_env() -> Self {
+        ColorEnv {
+            no_color: std::env::var_os("NO_COLOR"),
+            clicolor: std::env::var_os("CLICOLOR"),
+            clicolor_force: std::env::var_os("CLICOLOR_FORCE"),
+        }
+    }
+}
+
+/// Every colour decision goes through here. `flag` is `--color always|never` (or `--no-color`)
+/// and wins outright; then a non-empty NO_COLOR turns colour off and CLICOLOR_FORCE (other
+/// than 0) turns it on, NO_COLOR winning if both are set; then CLICOLOR=0 turns it off; and
+/// otherwise only a terminal gets colour.
+pub fn should_colorize(flag: Option<bool>, env: &ColorEnv, is_terminal: bool) -> bool {
+    if let Some(forced) = flag {
+        return forced;
+    }
+    let is = |value: &Option<OsString>, expected: &str| value.as_deref() == Some(OsStr::new(expected));
+    let non_empty = |value: &Option<OsString>| value.as_deref().is_some_and(|v| !v.is_empty());
+    if non_empty(&env.no_color) {
+        return false;
+    }
+    if non_empty(&env.clicolor_force) && !is(&env.clicolor_force, "0") {
+        return true;
+    }
+    if is(&env.clicolor, "0") {
+        return false;
+    }
+    is_terminal
+}
+
+pub fn paint(text: &str, style: Style) -> String {
+    if color_enabled() {
+        text.style(style).to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+/// Flat, colour-free view of a `RepoStatus` for machine consumers
+#[derive(Debug, Serialize)]
+pub struct RepoStatusJson {
+    pub branch: String,
+    pub detached: bool,
+    pub unborn: bool,
+    pub head_oid: String,
+    pub ahead: Option<usize>,
+    pub behind: Option<usize>,
+    pub upstream_gone: bool,
+    pub shallow: bool,
+    pub worktree_dirty: usize,
+    pub index_dirty: usize,
+    pub untracked: usize,
+    pub conflicts: usize,
+    pub renamed: usize,
+    pub stash: usize,
+    pub operation: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote: Option<RemoteStatusJson>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RemoteStatusJson {
+    pub ahead: Option<usize>,
+    pub behind: Option<usize>,
+    pub refreshed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fetch_error: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to_push: Option<usize>, // ahead of a separate push remote
+}
+
+impl From<&RepoStatus> for RepoStatusJson {
+    fn from(status: &RepoStatus) -> Self {
+        RepoStatusJson {
+            branch: status.branch_name(None),
+            detached: matches!(status.branch, BranchState::Detached(_)),
+            unborn: status.is_unborn(),
+            head_oid: status.head_oid.to_string(),
+            ahead: status.position.as_ref().map(|p| p.ahead),
+            behind: status.position.as_ref().map(|p| p.behind),
+            upstream_gone: status.upstream_gone,
+            shallow: status.shallow,
+            worktree_dirty: status.dirty.worktree,
+            index_dirty: status.dirty.index,
+            untracked: status.dirty.untracked,
+            conflicts: status.dirty.conflicts,
+            renamed: status.dirty.renamed,
+            stash: status.stash,
+            operation: status.operation.label(),
+            remote: status.remote_status.as_ref().map(|remote| RemoteStatusJson {
+                ahead: remote.position.as_ref().map(|p| p.ahead),
+                behind: remote.position.as_ref().map(|p| p.behind),
+                refreshed: remote.fetch.is_current(),
+                fetch_error: remote.outcome.and_then(|outcome| outcome.failure_label()),
+                to_push: remote.push_position.as_ref().map(|p| p.ahead),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DirStatusJson {
+    pub name: String,
+    pub broken: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(flatten)]
+    pub status: RepoStatusJson,
+}
+
+pub fn render_dir_json(results: &HashMap<String, RepoStatus>) -> Result<String, FuError> {
+    let mut rows: Vec<_> = results.iter().collect();
+    rows.sort_by(|a, b| a.0.cmp(b.0));
+    let entries: Vec<DirStatusJson> = rows
+        .into_iter()
+        .map(|(name, status)| DirStatusJson {
+            name: name.clone(),
+            broken: status.is_broken(),
+            error: status.error.clone(),
+            status: RepoStatusJson::from(status),
+        })
+        .collect();
+    Ok(serde_json::to_string(&entries)?)
+}
+
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// `-z`: one NUL-terminated record per repo, `name\tbranch\tdirty\tahead\tbehind`, with
+/// ahead/behind left empty when there is no upstream
+pub fn render_dir_null(results: &HashMap<String, RepoStatus>) -> String {
+    let mut rows: Vec<_> = results.iter().collect();
+    rows.sort_by(|a, b| a.0.cmp(b.0));
+    let mut out = String::new();
+    for (name, status) in rows {
+        let fields = [
+            name.clone(),
+            status.branch_name(None),
+            status.dirty.total().to_string(),
+            status.position.as_ref().map(|p| p.ahead.to_string()).unwrap_or_default(),
+            status.position.as_ref().map(|p| p.behind.to_string()).unwrap_or_default(),
+        ];
+        out.push_str(&fields.join("\t"));
+        out.push('\0');
+    }
+    out
+}
+
+/// `-z` for branches: `name\tdate\tahead\tbehind` records, ahead/behind only with --with-position
+pub fn render_branch_null(branches: &[BranchInfo]) -> String {
+    let mut out = String::new();
+    for branch in branches {
+        let fields = [
+            branch.name.clone(),
+            branch.iso_date.clone(),
+            branch.position.as_ref().map(|p| p.ahead.to_string()).unwrap_or_default(),
+            branch.position.as_ref().map(|p| p.behind.to_string()).unwrap_or_default(),
+        ];
+        out.push_str(&fields.join("\t"));
+        out.push('\0');
+    }
+    out
+}
+
+pub fn render_dir_csv(results: &HashMap<String, RepoStatus>) -> String {
+    let mut rows: Vec<_> = results.iter().collect();
+    rows.sort_by(|a, b| a.0.cmp(b.0));
+    let count = |value: Option<usize>| value.map(|v| v.to_string()).unwrap_or_default();
+
+    let mut out = String::from(
+        "repo,branch,detached,worktree,index,ahead,behind,remote_ahead,remote_behind,error\n",
+    );
+    for (name, status) in rows {
+        let remote = status.remote_status.as_ref().and_then(|r| r.position.as_ref());
+        let fields = [
+            name.clone(),
+            status.branch_name(None),
+            matches!(status.branch, BranchState::Detached(_)).to_string(),
+            status.dirty.worktree.to_string(),
+            status.dirty.index.to_string(),
+            count(status.position.as_ref().map(|p| p.ahead)),
+            count(status.position.as_ref().map(|p| p.behind)),
+            count(remote.map(|p| p.ahead)),
+            count(remote.map(|p| p.behind)),
+            match &status.error {
+                Some(error) => error.clone(),
+                None if status.is_broken() => "could not read repository status".to_string(),
+                None => String::new(),
+            },
+        ];
+        let fields: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
 
 pub fn timestamp_to_datetime(ts: i64) -> Result<DateTime<Utc>, FuError> {
     let timestamp = Utc
@@ -11,17 +239,262 @@ pub fn timestamp_to_datetime(ts: i64) -> Result<DateTime<Utc>, FuError> {
         .ok_or(FuError::Custom("Time out of range".to_string()))?;
     Ok(timestamp)
 }
-pub fn format_commit_time(ts: i64) -> Result<(String, String), FuError> {
+/// Which clock commit timestamps are shown on
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TimeZoneMode {
+    #[default]
+    Utc,
+    Local,
+    Commit, // the offset recorded in the commit itself
+}
+
+pub const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// How branches, tags and log entries render commit dates
+#[derive(Debug, Clone)]
+pub struct DateStyle {
+    pub zone: TimeZoneMode,
+    pub format: String, // strftime, checked with `check_date_format`
+}
+
+impl Default for DateStyle {
+    fn default() -> Self {
+        DateStyle {
+            zone: TimeZoneMode::default(),
+            format: DEFAULT_DATE_FORMAT.to_string(),
+        }
+    }
+}
+
+/// chrono only finds a bad specifier when formatting, so try it out on the current time
+pub fn check_date_format(format: &str) -> Result<(), String> {
+    use std::fmt::Write;
+    let mut out = String::new();
+    write!(out, "{}", Utc::now().format(format))
+        .map_err(|_| format!("invalid date format {:?}", format))
+}
+
+pub fn format_commit_time(time: git2::Time, style: &DateStyle) -> Result<(String, String), FuError> {
+    let layout = style.format.as_str();
+    let ts = time.seconds();
     let datetime = timestamp_to_datetime(ts)?;
-    let iso_date = format!("{}", datetime.format("%Y-%m-%d %H:%M:%S"));
-    let delta = format!(
-        "{}",
-        humantime::format_duration(std::time::Duration::from_secs(
-            (Utc::now().timestamp() - ts) as u64
-        ))
-    );
+    let iso_date = match style.zone {
+        TimeZoneMode::Utc => datetime.format(layout).to_string(),
+        TimeZoneMode::Local => datetime.with_timezone(&Local).format(layout).to_string(),
+        TimeZoneMode::Commit => {
+            let offset = FixedOffset::east_opt(time.offset_minutes() * 60)
+                .ok_or(FuError::Custom("Commit time offset out of range".to_string()))?;
+            datetime.with_timezone(&offset).format(layout).to_string()
+        }
+    };
+    // clock skew or rewritten history can date a commit after now
+    let delta = match u64::try_from(Utc::now().timestamp() - ts) {
+        Ok(secs) => format_age(std::time::Duration::from_secs(secs)),
+        Err(_) => "in the future".to_string(),
+    };
     Ok((iso_date, delta))
 }
+
+/// Humanised to whole seconds, e.g. `3h 12m 5s`
+pub fn format_age(age: std::time::Duration) -> String {
+    humantime::format_duration(std::time::Duration::from_secs(age.as_secs())).to_string()
+}
+pub fn strip_ansi(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: ESC [ params... final byte in 0x40..=0x7e
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('\u{40}'..='\u{7e}').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: ESC ] ... terminated by BEL or ESC \
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\u{07}' {
+                        break;
+                    }
+                    if c == '\u{1b}' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // two-byte escape, drop both
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Wraps each run of SGR escapes in shell-specific zero-width markers (`\[`/`\]` for bash,
+/// `%{`/`%}` for zsh) so prompt width is measured correctly
+pub fn wrap_escapes(rendered: &str, open: &str, close: &str) -> String {
+    let mut out = String::with_capacity(rendered.len());
+    let mut rest = rendered;
+    while let Some(start) = rest.find('\u{1b}') {
+        out.push_str(&rest[..start]);
+        let mut end = start;
+        while rest[end..].starts_with("\u{1b}[") {
+            match rest[end..].find('m') {
+                Some(m) => end += m + 1,
+                None => break,
+            }
+        }
+        if end == start {
+            // not an SGR sequence, copy the escape byte through untouched
+            out.push('\u{1b}');
+            rest = &rest[start + 1..];
+            continue;
+        }
+        out.push_str(open);
+        out.push_str(&rest[start..end]);
+        out.push_str(close);
+        rest = &rest[end..];
+    }
+    out.push_str(rest);
+    out
+}
+
+pub fn finish_output(rendered: String, strip: bool) -> String {
+    if strip { strip_ansi(&rendered) } else { rendered }
+}
+
+/// Writes `s` and a newline. A closed pipe comes back as `BrokenPipe` for the caller to settle,
+/// where `println!` would panic.
+pub fn write_line<W: Write>(w: &mut W, s: &str) -> io::Result<()> {
+    writeln!(w, "{}", s)
+}
+
+pub fn render_dir_metrics(results: &HashMap<String, RepoStatus>, fetch: bool) -> String {
+    let statuses = || results.values();
+    let behind = |status: &&RepoStatus| {
+        let upstream = status.position.as_ref().is_some_and(|p| p.behind > 0);
+        let remote = status
+            .remote_status
+            .as_ref()
+            .and_then(|r| r.position.as_ref())
+            .is_some_and(|p| p.behind > 0);
+        upstream || remote
+    };
+
+    let metrics = [
+        ("rgitfu_repos_total", "Repositories scanned", statuses().count()),
+        (
+            "rgitfu_repos_dirty",
+            "Repositories with worktree, index or untracked changes",
+            statuses().filter(|s| !s.dirty.is_clean()).count(),
+        ),
+        (
+            "rgitfu_repos_behind",
+            "Repositories behind their upstream or remote",
+            statuses().filter(behind).count(),
+        ),
+        (
+            "rgitfu_repos_broken",
+            "Repositories whose status could not be read",
+            statuses().filter(|s| s.is_broken()).count(),
+        ),
+        (
+            "rgitfu_fetch_timeouts",
+            "Fetches that did not complete in time",
+            if fetch {
+                statuses()
+                    .filter(|s| {
+                        s.remote_status.as_ref().is_some_and(|r| r.outcome == Some(FetchOutcome::TimedOut))
+                    })
+                    .count()
+            } else {
+                0
+            },
+        ),
+    ];
+
+    let mut out = String::new();
+    for (name, help, value) in metrics {
+        out.push_str(&format!("# HELP {} {}\n", name, help));
+        out.push_str(&format!("# TYPE {} gauge\n", name));
+        out.push_str(&format!("{} {}\n", name, value));
+    }
+    out
+}
+
+/// `--format prometheus`: gauges per repo, each family sorted by repo name
+pub fn render_dir_prometheus(results: &HashMap<String, RepoStatus>) -> String {
+    let mut rows: Vec<_> = results.iter().collect();
+    rows.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut out = String::new();
+    let mut family = |name: &str, help: &str, samples: Vec<(String, usize)>| {
+        out.push_str(&format!("# HELP {} {}\n# TYPE {} gauge\n", name, help, name));
+        for (labels, value) in samples {
+            out.push_str(&format!("{}{{{}}} {}\n", name, labels, value));
+        }
+    };
+    let repo = |name: &str| format!("repo=\"{}\"", prometheus_label(name));
+
+    let mut dirty = Vec::new();
+    for (name, status) in &rows {
+        let d = &status.dirty;
+        for (kind, count) in [
+            ("worktree", d.worktree),
+            ("index", d.index),
+            ("untracked", d.untracked),
+            ("conflicts", d.conflicts),
+            ("renamed", d.renamed),
+        ] {
+            dirty.push((format!("{},kind=\"{}\"", repo(name), kind), count));
+        }
+    }
+    family("rgitfu_repo_dirty", "Changed paths by kind", dirty);
+
+    // repos without an upstream have nothing to be ahead or behind of, so they're left out
+    let positions = || rows.iter().filter_map(|(name, status)| status.position.as_ref().map(|p| (repo(name), p)));
+    family(
+        "rgitfu_repo_ahead",
+        "Commits ahead of the upstream",
+        positions().map(|(labels, p)| (labels, p.ahead)).collect(),
+    );
+    family(
+        "rgitfu_repo_behind",
+        "Commits behind the upstream",
+        positions().map(|(labels, p)| (labels, p.behind)).collect(),
+    );
+    family(
+        "rgitfu_repo_broken",
+        "1 when the repo's status could not be read",
+        rows.iter().map(|(name, status)| (repo(name), usize::from(status.is_broken()))).collect(),
+    );
+    out
+}
+
+/// Label values may hold anything once backslashes, quotes and newlines are escaped
+fn prometheus_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+pub fn sparkline(buckets: &[usize], levels: &[char; 8]) -> String {
+    let max = buckets.iter().copied().max().unwrap_or(0);
+    buckets
+        .iter()
+        .map(|&count| {
+            if count == 0 {
+                ' '
+            } else {
+                levels[count * (levels.len() - 1) / max]
+            }
+        })
+        .collect()
+}
+
 pub fn standard_table_setup(plain_tables: bool) -> Table {
     let mut table = Table::new();
     table
@@ -33,5 +506,309 @@ pub fn standard_table_setup(plain_tables: bool) -> Table {
         ASCII_BORDERS_ONLY_CONDENSED
     };
     table.load_preset(table_style);
+    if color_enabled() {
+        table.enforce_styling();
+    } else {
+        table.force_no_tty();
+    }
     table
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::{FetchState, Position, RemoteStatus};
+    use crate::theme::Symbols;
+
+    #[test]
+    fn test_commit_time_zones() -> Result<(), FuError> {
+        // 2024-03-01 12:00:00 UTC, committed at +02:00
+        let time = git2::Time::new(1_709_294_400, 120);
+        let render = |zone| {
+            let style = DateStyle { zone, ..DateStyle::default() };
+            format_commit_time(time, &style).map(|(iso, _)| iso)
+        };
+
+        assert_eq!(render(TimeZoneMode::Utc)?, "2024-03-01 12:00:00");
+        assert_eq!(render(TimeZoneMode::Commit)?, "2024-03-01 14:00:00");
+        let local = Utc.timestamp_opt(1_709_294_400, 0).unwrap().with_timezone(&Local);
+        assert_eq!(render(TimeZoneMode::Local)?, local.format("%Y-%m-%d %H:%M:%S").to_string());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_line_surfaces_broken_pipe() {
+        struct ClosedPipe;
+        impl Write for ClosedPipe {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::Error::from(io::ErrorKind::BrokenPipe))
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let err = write_line(&mut ClosedPipe, "repo: OK").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        // and it reaches callers as an ordinary error rather than ending the process
+        let err: FuError = err.into();
+        assert!(matches!(err, FuError::IoError(ref io_err) if io_err.kind() == io::ErrorKind::BrokenPipe));
+
+        let mut out = Vec::new();
+        write_line(&mut out, "repo: OK").unwrap();
+        assert_eq!(out, b"repo: OK\n");
+    }
+
+    #[test]
+    fn test_future_commit_time() -> Result<(), FuError> {
+        let tomorrow = git2::Time::new(Utc::now().timestamp() + 24 * 60 * 60, 0);
+        assert_eq!(format_commit_time(tomorrow, &DateStyle::default())?.1, "in the future");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_date_format() -> Result<(), FuError> {
+        let time = git2::Time::new(1_709_294_400, 0);
+        let style = DateStyle { format: "%Y-%m-%d".to_string(), ..DateStyle::default() };
+        assert_eq!(format_commit_time(time, &style)?.0, "2024-03-01");
+
+        assert!(check_date_format("%Y-%m-%d").is_ok());
+        assert!(check_date_format("%Y-%!").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_should_colorize() {
+        let env = |no_color: Option<&str>, clicolor: Option<&str>, clicolor_force: Option<&str>| ColorEnv {
+            no_color: no_color.map(OsString::from),
+            clicolor: clicolor.map(OsString::from),
+            clicolor_force: clicolor_force.map(OsString::from),
+        };
+        let unset = ColorEnv::default();
+        assert!(should_colorize(None, &unset, true));
+        assert!(!should_colorize(None, &unset, false));
+
+        // NO_COLOR: any non-empty value
+        assert!(!should_colorize(None, &env(Some("1"), None, None), true));
+        assert!(should_colorize(None, &env(Some(""), None, None), true));
+
+        // CLICOLOR_FORCE colours a pipe too, unless it's 0 or NO_COLOR is also set
+        assert!(should_colorize(None, &env(None, None, Some("1")), false));
+        assert!(!should_colorize(None, &env(None, None, Some("0")), false));
+        assert!(!should_colorize(None, &env(Some("1"), None, Some("1")), true));
+        assert!(should_colorize(None, &env(None, Some("0"), Some("1")), false));
+
+        // CLICOLOR only ever narrows the terminal default
+        assert!(!should_colorize(None, &env(None, Some("0"), None), true));
+        assert!(should_colorize(None, &env(None, Some("1"), None), true));
+        assert!(!should_colorize(None, &env(None, Some("1"), None), false));
+
+        // --color always/never beat everything
+        assert!(should_colorize(Some(true), &env(Some("1"), Some("0"), None), false));
+        assert!(!should_colorize(Some(false), &env(None, None, Some("1")), true));
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(strip_ansi("\u{1b}[35mmain\u{1b}[39m|\u{1b}[32m✔\u{1b}[39m"), "main|✔");
+        assert_eq!(strip_ansi("\u{1b}]8;;http://x\u{1b}\\link\u{1b}]8;;\u{07}"), "link");
+        assert_eq!(strip_ansi("plain"), "plain");
+    }
+
+    fn sample_status(branch: BranchState) -> RepoStatus {
+        let mut status = RepoStatus::broken_state("unused".to_string());
+        status.branch = branch;
+        status.head_oid = git2::Oid::from_str("0123456789abcdef0123456789abcdef01234567").unwrap();
+        status.position = Some(Position { ahead: 1, behind: 2, diverged: true });
+        status
+    }
+
+    #[test]
+    fn test_null_separated_records() {
+        let mut results = HashMap::new();
+        let mut spaced = sample_status(BranchState::Named("main".to_string()));
+        spaced.dirty.worktree = 2;
+        spaced.dirty.untracked = 1;
+        results.insert("my repo\nwith newline".to_string(), spaced);
+        let mut no_upstream = sample_status(BranchState::Named("dev".to_string()));
+        no_upstream.position = None;
+        results.insert("plain".to_string(), no_upstream);
+
+        let out = render_dir_null(&results);
+        assert!(out.ends_with('\0'));
+        let records: Vec<Vec<&str>> = out
+            .split_terminator('\0')
+            .map(|record| record.split('\t').collect())
+            .collect();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0], ["my repo\nwith newline", "main", "3", "1", "2"]);
+        assert_eq!(records[1], ["plain", "dev", "0", "", ""]);
+    }
+
+    #[test]
+    fn test_repo_status_json_named_branch() {
+        let mut status = sample_status(BranchState::Named("main".to_string()));
+        status.remote_status = Some(RemoteStatus {
+            position: Some(Position { ahead: 0, behind: 3, diverged: false }),
+            fetch: FetchState::Refreshed,
+            outcome: Some(FetchOutcome::Fetched),
+            remote_positions: vec![],
+            push_position: None,
+        });
+        let value = serde_json::to_value(RepoStatusJson::from(&status)).unwrap();
+
+        for key in [
+            "branch", "detached", "head_oid", "ahead", "behind", "worktree_dirty", "index_dirty",
+            "remote",
+        ] {
+            assert!(value.get(key).is_some(), "missing {}", key);
+        }
+        assert_eq!(value["branch"], "main");
+        assert_eq!(value["detached"], false);
+        assert_eq!(value["ahead"], 1);
+        assert_eq!(value["remote"]["behind"], 3);
+        assert!(!value.to_string().contains('\u{1b}'));
+    }
+
+    #[test]
+    fn test_repo_status_json_detached() {
+        let status = sample_status(BranchState::Detached(None));
+        let value = serde_json::to_value(RepoStatusJson::from(&status)).unwrap();
+
+        assert_eq!(value["branch"], "0123456");
+        assert_eq!(value["detached"], true);
+        assert_eq!(value["head_oid"], "0123456789abcdef0123456789abcdef01234567");
+        assert!(value.get("remote").is_none());
+    }
+
+    #[test]
+    fn test_render_dir_metrics() {
+        let mut results = HashMap::new();
+        results.insert("broken".to_string(), RepoStatus::broken_state("broken-head".to_string()));
+        let metrics = render_dir_metrics(&results, false);
+        assert!(metrics.contains("\nrgitfu_repos_total 1\n"));
+        assert!(metrics.contains("\nrgitfu_repos_broken 1\n"));
+        assert!(metrics.contains("\nrgitfu_repos_dirty 0\n"));
+        assert!(metrics.contains("\nrgitfu_fetch_timeouts 0\n"));
+        assert!(!metrics.contains('\u{1b}'));
+    }
+
+    type Sample<'a> = (&'a str, Vec<(String, String)>, f64);
+
+    /// `name{k="v",...} value`, with label values escaped the way the exposition format wants
+    fn parse_sample(line: &str) -> Option<Sample<'_>> {
+        let (name, rest) = line.split_once('{')?;
+        let valid_name = name.chars().enumerate().all(|(i, c)| {
+            c.is_ascii_alphabetic() || c == '_' || c == ':' || (i > 0 && c.is_ascii_digit())
+        });
+        if name.is_empty() || !valid_name {
+            return None;
+        }
+        let mut labels = Vec::new();
+        let mut chars = rest.chars();
+        loop {
+            let key: String = chars.by_ref().take_while(|&c| c != '=').collect();
+            if chars.next()? != '"' {
+                return None;
+            }
+            let mut value = String::new();
+            loop {
+                match chars.next()? {
+                    '\\' => match chars.next()? {
+                        'n' => value.push('\n'),
+                        c @ ('\\' | '"') => value.push(c),
+                        _ => return None,
+                    },
+                    '"' => break,
+                    '\n' => return None,
+                    c => value.push(c),
+                }
+            }
+            labels.push((key, value));
+            match chars.next()? {
+                ',' => continue,
+                '}' => break,
+                _ => return None,
+            }
+        }
+        let value = chars.as_str().strip_prefix(' ')?.parse().ok()?;
+        Some((name, labels, value))
+    }
+
+    #[test]
+    fn test_render_dir_prometheus() {
+        let mut results = HashMap::new();
+        let mut dirty = sample_status(BranchState::Named("main".to_string()));
+        dirty.dirty.worktree = 3;
+        dirty.dirty.untracked = 1;
+        results.insert("work/\"quoted\"\\repo".to_string(), dirty);
+        let mut no_upstream = sample_status(BranchState::Named("dev".to_string()));
+        no_upstream.position = None;
+        results.insert("alpha".to_string(), no_upstream);
+        results.insert("broken".to_string(), RepoStatus::broken_state("broken-head".to_string()));
+
+        let out = render_dir_prometheus(&results);
+        assert!(!out.contains('\u{1b}'));
+        assert_eq!(out, render_dir_prometheus(&results));
+
+        let samples: Vec<_> = out
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(|line| parse_sample(line).unwrap_or_else(|| panic!("not a metric sample: {}", line)))
+            .collect();
+        assert_eq!(samples.len(), 3 * 5 + 1 + 1 + 3);
+        let value = |name: &str, repo: &str, kind: Option<&str>| {
+            samples
+                .iter()
+                .find(|(n, labels, _)| {
+                    *n == name
+                        && labels[0] == ("repo".to_string(), repo.to_string())
+                        && kind.is_none_or(|kind| labels[1] == ("kind".to_string(), kind.to_string()))
+                })
+                .map(|sample| sample.2)
+        };
+        assert_eq!(value("rgitfu_repo_dirty", "work/\"quoted\"\\repo", Some("worktree")), Some(3.0));
+        assert_eq!(value("rgitfu_repo_dirty", "work/\"quoted\"\\repo", Some("untracked")), Some(1.0));
+        assert_eq!(value("rgitfu_repo_ahead", "work/\"quoted\"\\repo", None), Some(1.0));
+        assert_eq!(value("rgitfu_repo_behind", "work/\"quoted\"\\repo", None), Some(2.0));
+        assert_eq!(value("rgitfu_repo_ahead", "alpha", None), None);
+        assert_eq!(value("rgitfu_repo_broken", "broken", None), Some(1.0));
+        assert_eq!(value("rgitfu_repo_broken", "alpha", None), Some(0.0));
+
+        let broken_rows: Vec<_> = out.lines().filter(|line| line.starts_with("rgitfu_repo_broken{")).collect();
+        assert_eq!(
+            broken_rows,
+            [
+                "rgitfu_repo_broken{repo=\"alpha\"} 0",
+                "rgitfu_repo_broken{repo=\"broken\"} 1",
+                "rgitfu_repo_broken{repo=\"work/\\\"quoted\\\"\\\\repo\"} 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wrap_escapes() {
+        let rendered = "(\u{1b}[35mmain\u{1b}[39m|\u{1b}[1m\u{1b}[31mAM\u{1b}[0m)";
+        assert_eq!(
+            wrap_escapes(rendered, "\\[", "\\]"),
+            "(\\[\u{1b}[35m\\]main\\[\u{1b}[39m\\]|\\[\u{1b}[1m\u{1b}[31m\\]AM\\[\u{1b}[0m\\])"
+        );
+        assert_eq!(
+            wrap_escapes("\u{1b}[32m✔\u{1b}[39m", "%{", "%}"),
+            "%{\u{1b}[32m%}✔%{\u{1b}[39m%}"
+        );
+        assert_eq!(wrap_escapes("(main|✔)", "\\[", "\\]"), "(main|✔)");
+    }
+
+    #[test]
+    fn test_sparkline() {
+        let levels = Symbols::UNICODE.spark;
+        assert_eq!(sparkline(&[0, 1, 4, 8, 2], &levels), " ▁▄█▂");
+        assert_eq!(sparkline(&[0, 0, 0], &levels), "   ");
+        assert_eq!(sparkline(&[], &levels), "");
+        assert_eq!(sparkline(&[0, 1, 4, 8, 2], &Symbols::ASCII.spark), " _~#.");
+    }
+}
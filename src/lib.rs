@@ -0,0 +1,43 @@
+//! Git status gathering behind the `r-git-fu` binary, usable on its own.
+//!
+//! ```
+//! use r_git_fu::{gather_git_repo, get_repo_state, BranchState, StatusOptions};
+//!
+//! let dir = tempfile::tempdir()?;
+//! git2::Repository::init(dir.path())?;
+//! std::fs::write(dir.path().join("notes.txt"), "draft\n")?;
+//!
+//! let repo = gather_git_repo(&dir.path().to_path_buf())?;
+//! let status = get_repo_state(&repo, &StatusOptions::default())?;
+//! assert!(matches!(status.branch, BranchState::Unborn(_)));
+//! assert_eq!(status.dirty.untracked, 1);
+//! # Ok::<(), r_git_fu::FuError>(())
+//! ```
+
+mod config;
+mod display;
+mod git;
+#[cfg(feature = "notify")]
+mod notify;
+mod primitives;
+mod template;
+mod theme;
+mod trace;
+
+// the binary's argument handling and output; not part of the library API
+#[doc(hidden)]
+pub mod cli;
+
+pub use display::{DateStyle, TimeZoneMode};
+pub use git::{gather_git_repo, get_branch_info, get_default_branch, get_multi_directory_status, get_repo_state};
+pub use primitives::{
+    BaseDivergence, BranchInfo, BranchState, CommitInfo, DirtyAttention, DirtyState, FetchBackend, FetchOutcome,
+    FetchState, FuError, HostFilter, Position, RemoteInfo, RemoteStatus, RepoOperation, RepoStatus,
+    StatusOptions, SubmoduleState, TagInfo, WorktreeInfo,
+};
+pub use theme::{Theme, ThemeColor};
+
+#[doc(hidden)]
+pub use display::set_color_enabled;
+#[doc(hidden)]
+pub use trace::set_level as set_log_level;
@@ -1,19 +1,101 @@
-mod cli;
-mod primitives;
-mod git;
-mod display;
+use r_git_fu::cli::{
+    dir_status, dump_branches, dump_log, dump_tags, fetch_repos, get_prompt, list_remotes, list_worktrees,
+    prune_branches, show_status, write_completions, Cli, Command,
+};
 
-use crate::cli::{dir_status, dump_branches, get_prompt, Cli, Command};
+use r_git_fu::{set_color_enabled, set_log_level};
+use r_git_fu::{FuError, RepoStatus};
+use clap::{CommandFactory, FromArgMatches};
+use std::io::ErrorKind;
+use std::process::ExitCode;
 
-use crate::primitives::FuError;
-use clap::Parser;
+/// `--exit-code`: 1 if any repo has changes to tracked files (conflicts and renames included),
+/// else 2 if any is out of sync (ahead or behind)
+fn status_exit_code<'a>(statuses: impl IntoIterator<Item = &'a RepoStatus>) -> u8 {
+    let mut code = 0;
+    for status in statuses {
+        if status.dirty.tracked_changes() > 0 {
+            return 1;
+        }
+        if status.is_out_of_sync() {
+            code = 2;
+        }
+    }
+    code
+}
+
+fn main() -> ExitCode {
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+    // before --plain, which must win over a config's plain_tables = false
+    let config = cli
+        .load_config(&matches)
+        .and_then(|()| cli.load_repo_list())
+        .and_then(|()| cli.expand_repo_path());
+    cli.apply_plain();
+    set_color_enabled(cli.color_enabled());
+    set_log_level(cli.log_level());
+
+    // commands that inspect repo status hand it back for --exit-code
+    let result: Result<Vec<RepoStatus>, FuError> =
+        config.and_then(|()| match cli.command {
+            Command::Prompt => get_prompt(&cli).map(|status| vec![status]),
+            Command::Status { .. } => show_status(&cli),
+            Command::Log => dump_log(&cli).map(|()| Vec::new()),
+            Command::Branches => dump_branches(&cli).map(|()| Vec::new()),
+            Command::DirStatus { .. } => dir_status(&cli).map(|results| results.into_values().collect()),
+            Command::Remotes => list_remotes(&cli).map(|()| Vec::new()),
+            Command::Tags => dump_tags(&cli).map(|()| Vec::new()),
+            Command::Worktrees => list_worktrees(&cli).map(|()| Vec::new()),
+            Command::Fetch => fetch_repos(&cli).map(|()| Vec::new()),
+            Command::Prune { yes } => prune_branches(&cli, yes).map(|()| Vec::new()),
+            Command::Completions { shell } => {
+                write_completions(shell, &mut std::io::stdout());
+                Ok(Vec::new())
+            }
+        });
+
+    match result {
+        Ok(statuses) if cli.exit_code => ExitCode::from(status_exit_code(&statuses)),
+        Ok(_) => ExitCode::SUCCESS,
+        // piped into `head` and friends: the reader has all it wanted
+        Err(FuError::IoError(err)) if err.kind() == ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(err) => {
+            // prompts are embedded in PS1, so failures there only show up in $?
+            if !matches!(cli.command, Command::Prompt) {
+                eprintln!("r-git-fu: {}", err);
+            }
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use r_git_fu::{DirtyState, Position};
 
-fn main() -> Result<(), FuError> {
-    let cli = Cli::parse();
+    #[test]
+    fn test_status_exit_code() {
+        let clean = RepoStatus::broken_state("main".to_string());
+        let mut dirty = RepoStatus::broken_state("main".to_string());
+        dirty.dirty = DirtyState { worktree: 1, index: 0, untracked: 0, conflicts: 0, renamed: 0 };
+        let mut behind = RepoStatus::broken_state("main".to_string());
+        behind.position = Some(Position { ahead: 0, behind: 2, diverged: false });
+        let mut untracked_only = RepoStatus::broken_state("main".to_string());
+        untracked_only.dirty.untracked = 3;
+        let mut conflicted = RepoStatus::broken_state("main".to_string());
+        conflicted.dirty.conflicts = 1;
+        let mut renamed_only = RepoStatus::broken_state("main".to_string());
+        renamed_only.dirty.renamed = 1;
 
-    match cli.command {
-        Command::Prompt => get_prompt(&cli.repo_path, cli.remote_status),
-        Command::Branches => dump_branches(&cli.repo_path, cli.plain_tables),
-        Command::DirStatus => dir_status(&cli.repo_path,cli.fetch, cli.timeout, cli.plain_tables),
+        assert_eq!(status_exit_code([&clean]), 0);
+        assert_eq!(status_exit_code([&untracked_only]), 0);
+        assert_eq!(status_exit_code([&dirty]), 1);
+        assert_eq!(status_exit_code([&conflicted]), 1);
+        assert_eq!(status_exit_code([&renamed_only]), 1);
+        assert_eq!(status_exit_code([&behind]), 2);
+        assert_eq!(status_exit_code([&behind, &dirty, &clean]), 1);
+        assert_eq!(status_exit_code([]), 0);
     }
 }
@@ -0,0 +1,147 @@
+//! `--watch --notify`: desktop alerts when a watched repo picks up work that isn't safe yet
+
+use crate::primitives::RepoStatus;
+use crate::trace::{debug, info};
+use std::collections::HashMap;
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RepoAlert {
+    BecameDirty,
+    GainedAhead(usize), // commits ahead now
+}
+
+impl RepoAlert {
+    pub fn message(&self) -> String {
+        match self {
+            RepoAlert::BecameDirty => "has uncommitted changes".to_string(),
+            RepoAlert::GainedAhead(ahead) => format!("is {} commit(s) ahead of its upstream", ahead),
+        }
+    }
+}
+
+/// Transitions between two successive snapshots, sorted by repo name. Repos missing from
+/// `previous` or broken in either snapshot are skipped, so a first pass or a failed read never
+/// fires; a repo that stays dirty or keeps the same ahead count fires nothing further.
+pub fn repo_alerts(
+    previous: &HashMap<String, RepoStatus>,
+    current: &HashMap<String, RepoStatus>,
+) -> Vec<(String, RepoAlert)> {
+    let mut alerts = Vec::new();
+    for (name, now) in current {
+        let Some(before) = previous.get(name) else { continue };
+        if before.is_broken() || now.is_broken() {
+            continue;
+        }
+        if before.dirty.is_clean() && !now.dirty.is_clean() {
+            alerts.push((name.clone(), RepoAlert::BecameDirty));
+        }
+        let ahead = |status: &RepoStatus| status.position.as_ref().map_or(0, |p| p.ahead);
+        if ahead(now) > ahead(before) {
+            alerts.push((name.clone(), RepoAlert::GainedAhead(ahead(now))));
+        }
+    }
+    alerts.sort_by(|a, b| a.0.cmp(&b.0));
+    alerts
+}
+
+/// Hands the alert to the platform notifier (`osascript` on macOS, `notify-send` elsewhere);
+/// a missing notifier is logged rather than stopping the watch
+pub fn send(repo: &str, alert: &RepoAlert) {
+    let body = format!("{} {}", repo, alert.message());
+    let mut command = if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        let script = format!("display notification {} with title \"r-git-fu\"", applescript_string(&body));
+        command.args(["-e", &script]);
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.args(["r-git-fu", &body]);
+        command
+    };
+    debug!("notifying: {}", body);
+    let status = command.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => info!("desktop notification for {} failed: {}", repo, status),
+        Err(err) => info!("cannot send desktop notification for {}: {}", repo, err),
+    }
+}
+
+/// `text` as an AppleScript string literal: backslashes and quotes escaped, line breaks and tabs
+/// as AppleScript's own escapes, and any other control character (an ANSI escape in a repo name,
+/// say) dropped, since AppleScript has no way to spell it
+fn applescript_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => {}
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::{BranchState, Position};
+
+    fn snapshot(worktree: usize, ahead: usize) -> RepoStatus {
+        let mut status = RepoStatus::broken_state("unused".to_string());
+        status.branch = BranchState::Named("main".to_string());
+        status.head_oid = git2::Oid::from_str("0123456789abcdef0123456789abcdef01234567").unwrap();
+        status.dirty.worktree = worktree;
+        status.position = Some(Position { ahead, behind: 0, diverged: false });
+        status
+    }
+
+    #[test]
+    fn test_repo_alerts_clean_to_dirty() {
+        let previous = HashMap::from([
+            ("app".to_string(), snapshot(0, 0)),
+            ("lib".to_string(), snapshot(2, 0)),
+        ]);
+        let current = HashMap::from([
+            ("app".to_string(), snapshot(1, 0)),
+            ("lib".to_string(), snapshot(3, 0)),
+            ("new".to_string(), snapshot(1, 0)),
+        ]);
+        assert_eq!(repo_alerts(&previous, &current), [("app".to_string(), RepoAlert::BecameDirty)]);
+
+        // the next pass sees the same state again and stays quiet
+        assert!(repo_alerts(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn test_repo_alerts_gained_ahead() {
+        let previous = HashMap::from([("app".to_string(), snapshot(0, 1))]);
+        let current = HashMap::from([("app".to_string(), snapshot(0, 2))]);
+        assert_eq!(repo_alerts(&previous, &current), [("app".to_string(), RepoAlert::GainedAhead(2))]);
+        assert!(repo_alerts(&current, &previous).is_empty());
+    }
+
+    #[test]
+    fn test_applescript_string() {
+        assert_eq!(applescript_string("app is dirty"), "\"app is dirty\"");
+        assert_eq!(applescript_string("say \"hi\" \\ bye"), "\"say \\\"hi\\\" \\\\ bye\"");
+        assert_eq!(applescript_string("two\nlines\ttab"), "\"two\\nlines\\ttab\"");
+        assert_eq!(applescript_string("\u{1b}[31mred\u{1b}[0m é"), "\"[31mred[0m é\"");
+    }
+
+    #[test]
+    fn test_repo_alerts_ignores_broken() {
+        let previous = HashMap::from([(
+            "app".to_string(),
+            RepoStatus::broken_state("broken-head".to_string()),
+        )]);
+        let current = HashMap::from([("app".to_string(), snapshot(1, 1))]);
+        assert!(repo_alerts(&previous, &current).is_empty());
+    }
+}
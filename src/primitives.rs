@@ -1,170 +1,745 @@
 use git2::Error as Git2Error;
-use owo_colors::OwoColorize;
+use crate::display::{paint, strip_ansi};
+use crate::theme::{Symbols, Theme};
+use owo_colors::Style;
 use std::env::VarError;
 use std::fmt::Display;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
 
 use std::io::Error as IoError;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 use thiserror::Error as ThisError;
 
-#[derive(Debug)]
+/// `git2::Oid` as its 40-character hex string
+#[cfg(feature = "serde")]
+mod oid_hex {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(oid: &git2::Oid, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&oid.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<git2::Oid, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        git2::Oid::from_str(&hex).map_err(D::Error::custom)
+    }
+}
+
+/// Whether the remote-tracking refs behind a `RemoteStatus` are current
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FetchState {
+    NotFetched, // no fetch requested, or it failed or did not finish in time
+    Refreshed,  // fetched during this run
+    Fresh,      // fetch skipped because the last one is younger than --fetch-interval
+}
+
+impl FetchState {
+    pub fn is_current(&self) -> bool {
+        *self != FetchState::NotFetched
+    }
+}
+
+/// How a fetch attempt ended, failures classified from git's stderr or libgit2's error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FetchOutcome {
+    Fetched,
+    TimedOut,
+    Network, // DNS, refused or dropped connections
+    Auth,    // rejected credentials or no access to the repo
+    Failed,  // anything else
+}
+
+impl FetchOutcome {
+    /// Short annotation for tables; `None` on success
+    pub fn failure_label(&self) -> Option<&'static str> {
+        match self {
+            FetchOutcome::Fetched => None,
+            FetchOutcome::TimedOut => Some("timeout"),
+            FetchOutcome::Network => Some("network"),
+            FetchOutcome::Auth => Some("auth"),
+            FetchOutcome::Failed => Some("failed"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RemoteStatus {
     pub position: Option<Position>,
-    pub refreshed: bool,
+    pub fetch: FetchState,
+    pub outcome: Option<FetchOutcome>, // set whenever a fetch was attempted
+    pub remote_positions: Vec<(String, Position)>, // every remote tracking this branch, with --all-remotes
+    pub push_position: Option<Position>, // against a push remote other than the compared one, with --push-status
 }
 
-#[derive(Debug)]
+impl RemoteStatus {
+    /// `⇡2` when commits are waiting for a separate push remote
+    pub fn push_marker(&self, symbols: &Symbols) -> String {
+        match &self.push_position {
+            Some(push) if push.ahead > 0 => format!("{}{}", symbols.push, push.ahead),
+            _ => "".to_string(),
+        }
+    }
+
+    /// `origin:↑1 upstream:↓3`, listing only the remotes the branch is out of sync with
+    pub fn compact_positions(&self, symbols: &Symbols) -> String {
+        self.remote_positions
+            .iter()
+            .filter(|(_, pos)| pos.ahead > 0 || pos.behind > 0)
+            .map(|(name, pos)| {
+                let (ahead, behind) = pos.string_markers(symbols);
+                format!("{}:{}{}", name, ahead, behind)
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Flags a shallow clone in the prompt and beside the dir-status Position
+pub const SHALLOW_MARKER: &str = "~shallow";
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct RepoStatus {
     pub branch: BranchState,
     pub dirty: DirtyState,
     pub position: Option<Position>,
+    pub upstream_gone: bool, // an upstream is configured but its tracking ref no longer exists
+    pub upstream: Option<String>, // e.g. origin/main, when requested
+    #[cfg_attr(feature = "serde", serde(with = "oid_hex"))]
     pub head_oid: git2::Oid,
+    pub head_time: i64, // HEAD commit's epoch seconds; 0 when unborn or broken
+    pub head_summary: Option<String>,
+    pub shallow: bool, // history is truncated, so ahead/behind counts can't be trusted
     pub remote_status: Option<RemoteStatus>,
+    pub last_fetch: Option<Duration>, // age of FETCH_HEAD, looked up along with the remote status
+    pub operation: RepoOperation,
+    pub stash: usize,
+    pub branch_stash: Option<usize>, // stashes made on the current branch, when requested
+    pub submodules: SubmoduleState,
+    pub error: Option<String>, // why a broken state could not be read
 }
 
 impl RepoStatus {
     pub fn broken_state(broken_state: String) -> Self {
         RepoStatus {
             branch: BranchState::Named(broken_state),
-            dirty: DirtyState {worktree:0, index:0},
+            dirty: DirtyState {worktree:0, index:0, untracked:0, conflicts:0, renamed:0},
             position: None,
+            upstream_gone: false,
+            upstream: None,
             head_oid: git2::Oid::zero(),
+            head_time: 0,
+            head_summary: None,
+            shallow: false,
             remote_status: None,
+            last_fetch: None,
+            operation: RepoOperation::None,
+            stash: 0,
+            branch_stash: None,
+            submodules: SubmoduleState::default(),
+            error: None,
         }
     }
 
-    pub fn branch_name(&self, colour_flag: bool) -> String {
+    pub fn is_broken(&self) -> bool {
+        self.head_oid.is_zero() && !self.is_unborn()
+    }
+
+    pub fn is_unborn(&self) -> bool {
+        matches!(self.branch, BranchState::Unborn(_))
+    }
+
+    /// Ahead or behind the upstream or the compared remote; `Position::diverged` is both at once
+    pub fn is_out_of_sync(&self) -> bool {
+        let out_of_sync = |position: Option<&Position>| {
+            position.is_some_and(|p| p.ahead > 0 || p.behind > 0)
+        };
+        out_of_sync(self.position.as_ref())
+            || out_of_sync(self.remote_status.as_ref().and_then(|r| r.position.as_ref()))
+    }
+
+    /// Known to match the remote: a remote status was gathered and nothing is ahead or behind
+    pub fn is_synced(&self) -> bool {
+        self.remote_status.as_ref().is_some_and(|r| r.position.is_some()) && !self.is_out_of_sync()
+    }
+
+    /// Anything a fleet view should surface: local changes, divergence, a paused operation or a broken repo
+    pub fn needs_attention(&self, attention: &DirtyAttention) -> bool {
+        self.dirty.needs_attention(attention)
+            || self.is_out_of_sync()
+            || self.operation != RepoOperation::None
+            || self.is_broken()
+    }
+
+    /// Uncoloured without a theme
+    pub fn branch_name(&self, theme: Option<&Theme>) -> String {
         let mut branch_str = match &self.branch {
-            BranchState::Named(name) => name.clone().to_string(),
-            BranchState::Detached => format!("{}", &self.head_oid.to_string()[..7])
-                .to_string(),
+            BranchState::Named(name) | BranchState::Unborn(name) => name.clone().to_string(),
+            BranchState::Detached(Some(tag)) => tag.clone(),
+            BranchState::Detached(None) => self.head_oid.to_string()[..7].to_string(),
         };
-        if colour_flag {
+        if let Some(theme) = theme {
             match &self.branch {
-                BranchState::Named(_name) => branch_str = branch_str.magenta().to_string(),
-                BranchState::Detached => branch_str = branch_str.cyan().to_string(),
+                BranchState::Named(_) | BranchState::Unborn(_) => {
+                    branch_str = paint(&branch_str, theme.branch.style())
+                }
+                BranchState::Detached(_) => branch_str = paint(&branch_str, theme.detached.style()),
             };
         }
         branch_str
     }
 
-    pub fn position_marker(&self) -> String {
+    pub fn position_marker(&self, theme: &Theme) -> String {
         match &self.position {
             Some(pos) => {
                 let mut s = String::new();
-                let (ahead, behind) = pos.string_markers();
+                let (ahead, behind) = pos.string_markers(&theme.symbols);
+                if pos.diverged {
+                    s.push_str(&paint(theme.symbols.diverged, theme.behind.style()));
+                }
                 if pos.ahead > 0 {
-                    s.push_str(&ahead.green().to_string());
+                    s.push_str(&paint(&ahead, theme.ahead.style()));
                 }
                 if pos.behind > 0 {
                     if !s.is_empty() {
                         s.push(' ');
                     }
-                    s.push_str(&behind.red().to_string());
+                    s.push_str(&paint(&behind, theme.behind.style()));
                 }
-                match &self.remote_status {
-                    Some(remote_status) => {
-                        if let Some(remote_position) = &remote_status.position {
-                            let (remote_ahead, remote_behind) = remote_position.string_markers();
-                            if remote_position.behind > 0 || remote_position.ahead > 0 {
-                                let remote_string = format!("[{}|{}]", remote_ahead, remote_behind);
-                                s.push_str(&remote_string.yellow().to_string());
-                            }
-                        }
+                let all_remotes = self
+                    .remote_status
+                    .as_ref()
+                    .map(|remote_status| remote_status.compact_positions(&theme.symbols))
+                    .unwrap_or_default();
+                if !all_remotes.is_empty() {
+                    s.push_str(&paint(&format!("[{}]", all_remotes), theme.remote.style()));
+                } else if let Some(remote_position) = self
+                    .remote_status
+                    .as_ref()
+                    .and_then(|remote_status| remote_status.position.as_ref())
+                {
+                    let (remote_ahead, remote_behind) = remote_position.string_markers(&theme.symbols);
+                    if remote_position.behind > 0 || remote_position.ahead > 0 {
+                        let remote_string = format!("[{}|{}]", remote_ahead, remote_behind);
+                        s.push_str(&paint(&remote_string, theme.remote.style()));
                     }
-                    None => {}
                 }
+                if let Some(remote_status) = &self.remote_status {
+                    s.push_str(&paint(&remote_status.push_marker(&theme.symbols), theme.remote.style()));
+                }
+                s.push_str(&paint(&self.upstream_marker(&theme.symbols), theme.remote.style()));
                 s
             }
+            None if self.upstream_gone => paint(&format!("{}upstream", theme.symbols.gone), theme.behind.style()),
             None => "".into(),
         }
     }
 
-    pub fn dirty_marker(&self) -> String {
-        if self.dirty.worktree == 0 && self.dirty.index == 0 {
-            return "✔".green().to_string();
+    /// `…origin/topic`, or just `↑` for the common case of a same-named branch on origin
+    pub fn upstream_marker(&self, symbols: &Symbols) -> String {
+        match (&self.upstream, &self.branch) {
+            (Some(upstream), BranchState::Named(name)) if *upstream == format!("origin/{}", name) => {
+                symbols.tracking.to_string()
+            }
+            (Some(upstream), _) => format!("{}{}", symbols.ellipsis, upstream),
+            (None, _) => "".to_string(),
         }
+    }
 
+    pub fn shallow_marker(&self) -> String {
+        if self.shallow {
+            paint(SHALLOW_MARKER, Style::new().yellow())
+        } else {
+            "".into()
+        }
+    }
+
+    pub fn operation_marker(&self) -> String {
+        match self.operation.label() {
+            Some(label) => paint(label, Style::new().red().bold()),
+            None => "".into(),
+        }
+    }
+
+    pub fn stash_marker(&self, symbols: &Symbols) -> String {
+        if self.stash == 0 {
+            return "".into();
+        }
+        let marker = match self.branch_stash {
+            Some(on_branch) => format!("{}{}({})", symbols.stash, self.stash, on_branch),
+            None => format!("{}{}", symbols.stash, self.stash),
+        };
+        paint(&marker, Style::new().blue())
+    }
+
+    pub fn submodule_marker(&self, symbols: &Symbols) -> String {
+        match self.submodules.needing_attention() {
+            0 => "".into(),
+            n => paint(&format!("{}{}", symbols.submodule, n), Style::new().yellow()),
+        }
+    }
+
+    pub fn dirty_marker(&self, attention: &DirtyAttention, theme: &Theme) -> String {
+        let symbols = &theme.symbols;
         let mut s = String::new();
 
-        s.push_str(&"●".red().to_string());
+        if self.dirty.conflicts > 0 {
+            s.push_str(&paint(&format!("{}{}", symbols.conflict, self.dirty.conflicts), Style::new().red().bold()));
+        } else if self.dirty.needs_attention(attention) {
+            s.push_str(&paint(symbols.dirty, theme.dirty.style()));
+        } else if self.is_synced() {
+            s.push_str(&paint(symbols.synced, theme.clean.style()));
+        } else {
+            s.push_str(&paint(symbols.clean, theme.clean.style()));
+        }
 
         if self.dirty.worktree > 0 {
-            s.push_str(&format!("{}", self.dirty.worktree).yellow().to_string());
+            s.push_str(&paint(&self.dirty.worktree.to_string(), Style::new().yellow()));
         }
 
         if self.dirty.index > 0 {
-            s.push_str(&format!("+{}", self.dirty.index).yellow().to_string());
+            s.push_str(&paint(&format!("+{}", self.dirty.index), Style::new().yellow()));
+        }
+
+        if self.dirty.renamed > 0 {
+            s.push_str(&paint(&format!("{}{}", symbols.renamed, self.dirty.renamed), Style::new().yellow()));
+        }
+
+        if self.dirty.untracked > 0 {
+            s.push_str(&paint(&format!("{}{}", symbols.untracked, self.dirty.untracked), Style::new().yellow()));
         }
 
         s
     }
-}
 
-impl Display for RepoStatus {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let branch_str = self.branch_name(true);
-        let position_str = self.position_marker();
-        let dirty = self.dirty_marker();
+    pub fn summary(&self, attention: &DirtyAttention, theme: &Theme) -> String {
+        let branch_str = format!("{}{}", theme.symbols.branch, self.branch_name(Some(theme)));
+        let position_str = self.position_marker(theme);
+        let dirty =
+            self.dirty_marker(attention, theme)
+            + &self.stash_marker(&theme.symbols)
+            + &self.submodule_marker(&theme.symbols);
 
-        let mut parts: Vec<String> = vec![branch_str];
+        let operation_str = self.operation_marker();
+
+        let mut parts: Vec<String> = vec![branch_str, self.shallow_marker()];
+        if !operation_str.is_empty() {
+            parts.push(format!("|{}", operation_str));
+        }
         if !position_str.is_empty() || !dirty.is_empty() {
             parts.push(format!("{}|{}", position_str, dirty));
         }
 
-        write!(f, "({})", parts.join(""))
+        format!("({})", parts.join(""))
     }
 }
 
-#[derive(Debug)]
+impl RepoStatus {
+    /// The one-line summary with the default attention and theme
+    pub fn write_to<W: Write>(&self, w: &mut W, color: bool) -> std::io::Result<()> {
+        self.write_summary_to(w, color, &DirtyAttention::default(), &Theme::default())
+    }
+
+    pub fn write_summary_to<W: Write>(
+        &self,
+        w: &mut W,
+        color: bool,
+        attention: &DirtyAttention,
+        theme: &Theme,
+    ) -> std::io::Result<()> {
+        let summary = self.summary(attention, theme);
+        if color {
+            w.write_all(summary.as_bytes())
+        } else {
+            w.write_all(strip_ansi(&summary).as_bytes())
+        }
+    }
+}
+
+impl Display for RepoStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut buf = Vec::new();
+        self.write_to(&mut buf, true).map_err(|_| std::fmt::Error)?;
+        f.write_str(&String::from_utf8_lossy(&buf))
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Position {
     pub ahead: usize,
     pub behind: usize,
+    pub diverged: bool, // commits on both sides, so neither fast-forwards to the other
 }
 
 impl Position {
-    pub fn string_markers(&self) -> (String, String) {
+    pub fn string_markers(&self, symbols: &Symbols) -> (String, String) {
         let (mut ahead, mut behind) = (String::new(), String::new());
         if self.ahead > 0 {
-            ahead.push_str(&format!("↑{}", self.ahead));
+            ahead.push_str(&format!("{}{}", symbols.ahead, self.ahead));
         }
         if self.behind > 0 {
-            behind.push_str(&format!("↓{}", self.behind));
+            behind.push_str(&format!("{}{}", symbols.behind, self.behind));
         }
         (ahead, behind)
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum RepoOperation {
+    None,
+    Merge,
+    Revert,
+    CherryPick,
+    Bisect,
+    Rebase,
+    ApplyMailbox,
+    ApplyMailboxOrRebase,
+}
+
+impl RepoOperation {
+    pub fn label(&self) -> Option<&'static str> {
+        match self {
+            RepoOperation::None => None,
+            RepoOperation::Merge => Some("MERGING"),
+            RepoOperation::Revert => Some("REVERTING"),
+            RepoOperation::CherryPick => Some("CHERRY-PICKING"),
+            RepoOperation::Bisect => Some("BISECTING"),
+            RepoOperation::Rebase => Some("REBASE"),
+            RepoOperation::ApplyMailbox => Some("AM"),
+            RepoOperation::ApplyMailboxOrRebase => Some("AM/REBASE"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "state", content = "name"))]
 pub enum BranchState {
     Named(String),
-    Detached,
+    Unborn(String), // HEAD names a branch that has no commits yet
+    Detached(Option<String>), // tag pointing at HEAD, if any
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct DirtyState {
-    pub worktree: usize, // number of uncommitted changes in worktree
-    pub index: usize,    // number of staged changes
+    pub worktree: usize,  // number of uncommitted changes to tracked files in worktree
+    pub index: usize,     // number of staged changes
+    pub untracked: usize, // number of new files git doesn't know about yet
+    pub conflicts: usize, // unmerged paths left by a merge, rebase or cherry-pick
+    pub renamed: usize,   // staged renames, counted here rather than in `index`
+}
+
+impl DirtyState {
+    /// Every changed path, untracked included
+    pub fn total(&self) -> usize {
+        self.tracked_changes() + self.untracked
+    }
+
+    /// Changes to files git already tracks: everything but untracked
+    pub fn tracked_changes(&self) -> usize {
+        self.worktree + self.index + self.conflicts + self.renamed
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.total() == 0
+    }
+
+    pub fn needs_attention(&self, attention: &DirtyAttention) -> bool {
+        // conflicts block any further work, whatever categories were asked for
+        self.conflicts > 0
+            || (attention.worktree && self.worktree > 0)
+            || (attention.index && self.index + self.renamed > 0)
+            || (attention.untracked && self.untracked > 0)
+    }
+}
+
+/// Submodules that differ from what the superproject records, each counted once
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SubmoduleState {
+    pub modified: usize,      // checked out at the recorded commit but with local changes
+    pub uninitialized: usize, // registered but never cloned/checked out
+    pub out_of_date: usize,   // checked out at a different commit than the superproject records
+}
+
+impl SubmoduleState {
+    pub fn needing_attention(&self) -> usize {
+        self.modified + self.uninitialized + self.out_of_date
+    }
+}
+
+/// How `--fetch` talks to remotes; the git CLI falls back to libgit2 when it isn't on PATH
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum FetchBackend {
+    #[default]
+    Cli,
+    Libgit2,
+}
+
+/// What `get_repo_state` inspects; the default is a local-only look like the prompt's
+#[derive(Debug, Clone)]
+pub struct StatusOptions {
+    pub fetch: bool,
+    pub remote_status: bool,
+    pub timeout: Duration,
+    pub fetch_interval: Option<Duration>,
+    pub remote: Option<String>, // defaults to the branch's upstream remote, then origin
+    pub all_remotes: bool,
+    pub push_status: bool,
+    pub include_stash: bool,
+    pub branch_stashes: bool,
+    pub include_submodules: bool,
+    pub ignore_submodules: bool, // leave submodule changes out of the dirty counts
+    pub pathspecs: Vec<String>,  // only count changes under these; empty means the whole repo
+    pub upstream_name: bool,
+    pub fetch_backend: FetchBackend,
+}
+
+impl Default for StatusOptions {
+    fn default() -> Self {
+        StatusOptions {
+            fetch: false,
+            remote_status: false,
+            timeout: Duration::from_millis(2500),
+            fetch_interval: None,
+            remote: None,
+            all_remotes: false,
+            push_status: false,
+            include_stash: true,
+            branch_stashes: false,
+            include_submodules: true,
+            ignore_submodules: false,
+            pathspecs: Vec::new(),
+            upstream_name: false,
+            fetch_backend: FetchBackend::Cli,
+        }
+    }
+}
+
+impl StatusOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn fetch(mut self, fetch: bool) -> Self {
+        self.fetch = fetch;
+        self
+    }
+
+    pub fn remote_status(mut self, remote_status: bool) -> Self {
+        self.remote_status = remote_status;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn fetch_interval(mut self, fetch_interval: Option<Duration>) -> Self {
+        self.fetch_interval = fetch_interval;
+        self
+    }
+
+    pub fn remote(mut self, remote: Option<String>) -> Self {
+        self.remote = remote;
+        self
+    }
+
+    pub fn all_remotes(mut self, all_remotes: bool) -> Self {
+        self.all_remotes = all_remotes;
+        self
+    }
+
+    pub fn push_status(mut self, push_status: bool) -> Self {
+        self.push_status = push_status;
+        self
+    }
+
+    pub fn include_stash(mut self, include_stash: bool) -> Self {
+        self.include_stash = include_stash;
+        self
+    }
+
+    pub fn branch_stashes(mut self, branch_stashes: bool) -> Self {
+        self.branch_stashes = branch_stashes;
+        self
+    }
+
+    pub fn include_submodules(mut self, include_submodules: bool) -> Self {
+        self.include_submodules = include_submodules;
+        self
+    }
+
+    pub fn ignore_submodules(mut self, ignore_submodules: bool) -> Self {
+        self.ignore_submodules = ignore_submodules;
+        self
+    }
+
+    pub fn pathspecs(mut self, pathspecs: Vec<String>) -> Self {
+        self.pathspecs = pathspecs;
+        self
+    }
+
+    pub fn upstream_name(mut self, upstream_name: bool) -> Self {
+        self.upstream_name = upstream_name;
+        self
+    }
+
+    pub fn fetch_backend(mut self, fetch_backend: FetchBackend) -> Self {
+        self.fetch_backend = fetch_backend;
+        self
+    }
+}
+
+/// Which dirty categories count as "needs attention" for colouring and the clean tick
+#[derive(Debug, Clone, Copy)]
+pub struct DirtyAttention {
+    pub worktree: bool,
+    pub index: bool,
+    pub untracked: bool,
+}
+
+impl Default for DirtyAttention {
+    fn default() -> Self {
+        DirtyAttention {
+            worktree: true,
+            index: true,
+            untracked: true,
+        }
+    }
+}
+
+/// Restricts directory scans to repos whose remote points at particular hosts
+#[derive(Debug, Clone, Default)]
+pub struct HostFilter {
+    pub allowed: Vec<String>,
+    pub denied: Vec<String>,
+}
+
+impl HostFilter {
+    pub fn is_active(&self) -> bool {
+        !self.allowed.is_empty() || !self.denied.is_empty()
+    }
+
+    pub fn permits(&self, host: Option<&str>) -> bool {
+        if !self.is_active() {
+            return true;
+        }
+        let Some(host) = host else {
+            return false;
+        };
+        let matches = |hosts: &[String]| hosts.iter().any(|h| h.eq_ignore_ascii_case(host));
+        if matches(&self.denied) {
+            return false;
+        }
+        self.allowed.is_empty() || matches(&self.allowed)
+    }
+}
+
+/// A linked worktree; `status` is `None` when its checkout can't be opened
+#[derive(Debug)]
+pub struct WorktreeInfo {
+    pub name: String,
+    pub path: PathBuf,
+    pub locked: bool,
+    pub status: Option<RepoStatus>,
+}
+
+impl WorktreeInfo {
+    pub fn label(&self) -> Option<&'static str> {
+        match (&self.status, self.locked) {
+            (None, _) => Some("prunable"),
+            (Some(_), true) => Some("locked"),
+            (Some(_), false) => None,
+        }
+    }
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct BranchInfo {
     pub name: String,
     pub commit_time: i64,
     pub iso_date: String,
     pub delta: String,
+    pub activity: Option<Vec<usize>>, // commits per day, oldest first
+    pub author_name: Option<String>,
+    pub author_email: Option<String>,
+    pub position: Option<Position>, // against the branch's upstream, with --with-position
+    pub base: Option<BaseDivergence>, // against the --vs branch
 }
+
+/// Where a branch stands relative to a base branch such as `main`
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum BaseDivergence {
+    Diverged(Position),
+    Unrelated, // no common ancestor, so there's nothing to count from
+}
+
+impl BranchInfo {
+    pub fn author(&self) -> &str {
+        self.author_name
+            .as_deref()
+            .or(self.author_email.as_deref())
+            .unwrap_or("unknown")
+    }
+}
+
 impl Display for BranchInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         format!(
             "{} {} {}",
-            &self.iso_date.green(),
-            &self.delta.blue(),
-            &self.name.white()
+            paint(&self.iso_date, Style::new().green()),
+            paint(&self.delta, Style::new().blue()),
+            paint(&self.name, Style::new().white())
         )
         .fmt(f)
     }
 }
 
+#[derive(Debug)]
+pub struct CommitInfo {
+    pub oid: git2::Oid,
+    pub iso_date: String,
+    pub delta: String,
+    pub summary: String,
+}
+
+impl CommitInfo {
+    pub fn short_oid(&self) -> String {
+        self.oid.to_string()[..7].to_string()
+    }
+}
+
+#[derive(Debug)]
+pub struct RemoteInfo {
+    pub name: String,
+    pub fetch_url: Option<String>,
+    pub push_url: Option<String>,
+    pub position: Option<Position>, // current branch against `<remote>/<branch>`, if tracked
+}
+
+#[derive(Debug)]
+pub struct TagInfo {
+    pub name: String,
+    pub commit_time: i64,
+    pub iso_date: String,
+    pub delta: String,
+    pub tagger: Option<String>,  // annotated tags only
+    pub message: Option<String>, // first line of an annotated tag's message
+}
+
 #[derive(ThisError, Debug)]
 pub enum FuError {
     #[error("{0}")]
@@ -178,4 +753,123 @@ pub enum FuError {
 
     #[error(transparent)]
     IoError(#[from] IoError),
+
+    #[error(transparent)]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("invalid config: {0}")]
+    ConfigError(#[from] toml::de::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gone_upstream_marker() {
+        let mut status = fixture_status();
+        status.position = None;
+        status.upstream_gone = true;
+        assert_eq!(strip_ansi(&status.summary(&DirtyAttention::default(), &Theme::default())), "(main✗upstream|●1)");
+    }
+
+    #[test]
+    fn test_synced_marker() {
+        let summary = |status: &RepoStatus| strip_ansi(&status.summary(&DirtyAttention::default(), &Theme::default()));
+        let remote = |ahead, behind| RemoteStatus {
+            position: Some(Position { ahead, behind, diverged: ahead > 0 && behind > 0 }),
+            fetch: FetchState::Fresh,
+            outcome: None,
+            remote_positions: vec![],
+            push_position: None,
+        };
+
+        // dirty, whatever the remote says
+        let mut status = fixture_status();
+        status.position = Some(Position { ahead: 0, behind: 0, diverged: false });
+        status.remote_status = Some(remote(0, 0));
+        assert_eq!(summary(&status), "(main|●1)");
+
+        // clean, remote unknown
+        status.dirty = DirtyState { worktree: 0, index: 0, untracked: 0, conflicts: 0, renamed: 0 };
+        status.remote_status = None;
+        assert_eq!(summary(&status), "(main|✔)");
+        assert!(!status.is_synced());
+
+        // clean and level with the remote
+        status.remote_status = Some(remote(0, 0));
+        assert_eq!(summary(&status), "(main|≡)");
+        assert!(status.is_synced());
+
+        // clean but behind the remote is not in sync
+        status.remote_status = Some(remote(0, 3));
+        assert_eq!(summary(&status), "(main[|↓3]|✔)");
+    }
+
+    #[test]
+    fn test_upstream_marker() {
+        let mut status = fixture_status();
+        assert_eq!(status.upstream_marker(&Symbols::UNICODE), "");
+        status.upstream = Some("origin/main".to_string());
+        assert_eq!(status.upstream_marker(&Symbols::UNICODE), "↑");
+        status.upstream = Some("upstream/trunk".to_string());
+        assert_eq!(status.upstream_marker(&Symbols::UNICODE), "…upstream/trunk");
+        assert_eq!(strip_ansi(&status.summary(&DirtyAttention::default(), &Theme::default())), "(main↑2…upstream/trunk|●1)");
+    }
+
+    fn fixture_status() -> RepoStatus {
+        let mut status = RepoStatus::broken_state("unused".to_string());
+        status.branch = BranchState::Named("main".to_string());
+        status.head_oid = git2::Oid::from_str("0123456789abcdef0123456789abcdef01234567").unwrap();
+        status.dirty = DirtyState { worktree: 1, index: 0, untracked: 0, conflicts: 0, renamed: 0 };
+        status.position = Some(Position { ahead: 2, behind: 0, diverged: false });
+        status
+    }
+
+    #[test]
+    fn test_write_to_buffer() -> Result<(), FuError> {
+        let status = fixture_status();
+
+        let mut plain = Vec::new();
+        status.write_to(&mut plain, false)?;
+        assert_eq!(plain, "(main↑2|●1)".as_bytes());
+        assert!(!plain.contains(&0x1b));
+
+        let mut colored = Vec::new();
+        status.write_to(&mut colored, true)?;
+        assert!(colored.contains(&0x1b));
+        assert_eq!(strip_ansi(&String::from_utf8_lossy(&colored)).as_bytes(), plain);
+        assert_eq!(status.to_string().as_bytes(), colored);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() -> Result<(), FuError> {
+        let mut status = RepoStatus::broken_state("unused".to_string());
+        status.branch = BranchState::Detached(Some("v1.0.0".to_string()));
+        status.head_oid = git2::Oid::from_str("0123456789abcdef0123456789abcdef01234567")?;
+        status.dirty = DirtyState { worktree: 1, index: 2, untracked: 3, conflicts: 0, renamed: 0 };
+        status.position = Some(Position { ahead: 1, behind: 0, diverged: false });
+        status.operation = RepoOperation::Rebase;
+        status.remote_status = Some(RemoteStatus {
+            position: Some(Position { ahead: 0, behind: 4, diverged: false }),
+            fetch: FetchState::Fresh,
+            outcome: None,
+            remote_positions: vec![("origin".to_string(), Position { ahead: 0, behind: 4, diverged: false })],
+            push_position: None,
+        });
+
+        let json = serde_json::to_value(&status)?;
+        assert_eq!(json["head_oid"], "0123456789abcdef0123456789abcdef01234567");
+        assert_eq!(json["branch"]["state"], "Detached");
+        assert_eq!(json["branch"]["name"], "v1.0.0");
+
+        let restored: RepoStatus = serde_json::from_value(json.clone())?;
+        assert_eq!(restored.head_oid, status.head_oid);
+        assert_eq!(serde_json::to_value(&restored)?, json);
+
+        Ok(())
+    }
 }
@@ -0,0 +1,100 @@
+use crate::primitives::{DirtyAttention, RepoStatus};
+use crate::theme::Theme;
+
+fn render_token(
+    token: &str,
+    status: &RepoStatus,
+    attention: &DirtyAttention,
+    theme: &Theme,
+) -> Option<String> {
+    let (ahead, behind) = match &status.position {
+        Some(position) => position.string_markers(&theme.symbols),
+        None => (String::new(), String::new()),
+    };
+    let value = match token {
+        "branch" => format!("{}{}", theme.symbols.branch, status.branch_name(Some(theme))),
+        "ahead" => ahead,
+        "behind" => behind,
+        "dirty" if status.dirty.is_clean() => String::new(),
+        "dirty" => status.dirty_marker(attention, theme),
+        "stash" => status.stash_marker(&theme.symbols),
+        "operation" => status.operation_marker(),
+        "oid" => status.head_oid.to_string()[..7].to_string(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+/// Substitutes `{token}`s from the status; `{{`/`}}` are literal braces and unknown tokens pass through
+pub fn render_template(
+    template: &str,
+    status: &RepoStatus,
+    attention: &DirtyAttention,
+    theme: &Theme,
+) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(idx) = rest.find(['{', '}']) {
+        out.push_str(&rest[..idx]);
+        let tail = &rest[idx..];
+
+        if let Some(after) = tail.strip_prefix("{{").or_else(|| tail.strip_prefix("}}")) {
+            out.push_str(&tail[..1]);
+            rest = after;
+        } else if let Some(after) = tail.strip_prefix('}') {
+            out.push('}');
+            rest = after;
+        } else {
+            let after_open = &tail[1..];
+            let token = after_open
+                .find(['{', '}'])
+                .filter(|&end| after_open[end..].starts_with('}'))
+                .map(|end| &after_open[..end]);
+            let rendered = token.and_then(|token| Some((token, render_token(token, status, attention, theme)?)));
+            match rendered {
+                Some((token, value)) => {
+                    out.push_str(&value);
+                    rest = &after_open[token.len() + 1..];
+                }
+                None => {
+                    // unknown or unterminated: keep the brace and let the rest copy through
+                    out.push('{');
+                    rest = after_open;
+                }
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::display::strip_ansi;
+    use crate::primitives::{BranchState, Position};
+
+    fn sample_status() -> RepoStatus {
+        let mut status = RepoStatus::broken_state("main".to_string());
+        status.branch = BranchState::Named("main".to_string());
+        status.head_oid = git2::Oid::from_str("abcdef0123456789abcdef0123456789abcdef01").unwrap();
+        status.position = Some(Position { ahead: 2, behind: 0, diverged: false });
+        status
+    }
+
+    #[test]
+    fn test_render_template() {
+        let status = sample_status();
+        let attention = DirtyAttention::default();
+        let render_template = |template: &str, status: &RepoStatus, attention: &DirtyAttention| {
+            strip_ansi(&render_template(template, status, attention, &Theme::default()))
+        };
+
+        assert_eq!(render_template("{branch}{ahead}{behind}", &status, &attention), "main↑2");
+        assert_eq!(render_template("[{oid}] {dirty}{stash}", &status, &attention), "[abcdef0] ");
+        assert_eq!(render_template("{branch} {nope}", &status, &attention), "main {nope}");
+        assert_eq!(render_template("{{branch}} {", &status, &attention), "{branch} {");
+        assert_eq!(render_template("}}{ {branch}", &status, &attention), "}{ main");
+    }
+}
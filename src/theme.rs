@@ -0,0 +1,193 @@
+use owo_colors::Style;
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeColor {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl ThemeColor {
+    pub fn style(&self) -> Style {
+        match self {
+            ThemeColor::Black => Style::new().black(),
+            ThemeColor::Red => Style::new().red(),
+            ThemeColor::Green => Style::new().green(),
+            ThemeColor::Yellow => Style::new().yellow(),
+            ThemeColor::Blue => Style::new().blue(),
+            ThemeColor::Magenta => Style::new().magenta(),
+            ThemeColor::Cyan => Style::new().cyan(),
+            ThemeColor::White => Style::new().white(),
+        }
+    }
+
+    pub fn table_color(&self) -> comfy_table::Color {
+        match self {
+            ThemeColor::Black => comfy_table::Color::Black,
+            ThemeColor::Red => comfy_table::Color::Red,
+            ThemeColor::Green => comfy_table::Color::Green,
+            ThemeColor::Yellow => comfy_table::Color::Yellow,
+            ThemeColor::Blue => comfy_table::Color::Blue,
+            ThemeColor::Magenta => comfy_table::Color::Magenta,
+            ThemeColor::Cyan => comfy_table::Color::Cyan,
+            ThemeColor::White => comfy_table::Color::White,
+        }
+    }
+}
+
+/// Marker glyphs used by the prompt and tables
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Symbols {
+    pub branch: &'static str, // prefix before the branch name
+    pub ahead: &'static str,
+    pub behind: &'static str,
+    pub dirty: &'static str,
+    pub clean: &'static str,
+    pub synced: &'static str, // clean and level with the remote, under --remote-status
+    pub conflict: &'static str,
+    pub renamed: &'static str,
+    pub untracked: &'static str,
+    pub stash: &'static str,
+    pub submodule: &'static str,
+    pub gone: &'static str,
+    pub tracking: &'static str, // upstream is the same-named branch on origin
+    pub push: &'static str,     // commits not yet on a separate push remote
+    pub diverged: &'static str, // ahead and behind at once: needs a merge or rebase
+    pub ellipsis: &'static str,
+    pub unrelated: &'static str,
+    pub spark: [char; 8],
+}
+
+impl Symbols {
+    pub const UNICODE: Symbols = Symbols {
+        branch: "",
+        ahead: "↑",
+        behind: "↓",
+        dirty: "●",
+        clean: "✔",
+        synced: "≡",
+        conflict: "✖",
+        renamed: "→",
+        untracked: "…",
+        stash: "⚑",
+        submodule: "⊂",
+        gone: "✗",
+        tracking: "↑",
+        push: "⇡",
+        diverged: "⇅",
+        ellipsis: "…",
+        unrelated: "∞",
+        spark: ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'],
+    };
+
+    /// `--ascii`, for terminals and fonts that show the Unicode markers as boxes
+    pub const ASCII: Symbols = Symbols {
+        branch: "",
+        ahead: "^",
+        behind: "v",
+        dirty: "*",
+        clean: "ok",
+        synced: "=",
+        conflict: "!",
+        renamed: ">",
+        untracked: "?",
+        stash: "$",
+        submodule: "@",
+        gone: "x",
+        tracking: "^",
+        push: "p",
+        diverged: "<>",
+        ellipsis: "...",
+        unrelated: "inf",
+        spark: ['_', '.', '-', '~', '=', '+', '*', '#'],
+    };
+
+    /// Nerd Font private-use glyphs; needs a patched font
+    pub const NERD: Symbols = Symbols {
+        branch: "\u{e0a0} ",
+        ahead: "\u{f077}",
+        behind: "\u{f078}",
+        dirty: "\u{f111}",
+        clean: "\u{f00c}",
+        synced: "\u{f021}",
+        conflict: "\u{f00d}",
+        renamed: "\u{f061}",
+        untracked: "\u{f128}",
+        stash: "\u{f01c}",
+        submodule: "\u{f414}",
+        gone: "\u{f05e}",
+        tracking: "\u{f077}",
+        push: "\u{f093}",
+        diverged: "\u{f0ec}",
+        ellipsis: "…",
+        unrelated: "∞",
+        spark: ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'],
+    };
+}
+
+impl Default for Symbols {
+    fn default() -> Self {
+        Symbols::UNICODE
+    }
+}
+
+/// Colours for the prompt segments and status table, loaded from the `[theme]` config section
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub branch: ThemeColor,
+    pub detached: ThemeColor,
+    pub dirty: ThemeColor,
+    pub clean: ThemeColor,
+    pub ahead: ThemeColor,
+    pub behind: ThemeColor,
+    pub remote: ThemeColor,
+    #[serde(skip)]
+    pub symbols: Symbols,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            branch: ThemeColor::Magenta,
+            detached: ThemeColor::Cyan,
+            dirty: ThemeColor::Red,
+            clean: ThemeColor::Green,
+            ahead: ThemeColor::Green,
+            behind: ThemeColor::Red,
+            remote: ThemeColor::Yellow,
+            symbols: Symbols::UNICODE,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::RepoStatus;
+
+    #[test]
+    fn test_custom_theme_changes_branch_colour() {
+        let status = RepoStatus::broken_state("main".to_string());
+
+        let default_branch = status.branch_name(Some(&Theme::default()));
+        assert!(default_branch.contains("\u{1b}[35m"));
+
+        let theme = Theme {
+            branch: ThemeColor::Blue,
+            ..Theme::default()
+        };
+        let themed_branch = status.branch_name(Some(&theme));
+        assert!(themed_branch.contains("\u{1b}[34m"));
+        assert!(!themed_branch.contains("\u{1b}[35m"));
+
+        assert_eq!(status.branch_name(None), "main");
+    }
+}
@@ -0,0 +1,85 @@
+//! Diagnostics on stderr, silent unless `--verbose`/`--debug` or `R_GIT_FU_LOG` turn them on.
+//!
+//! A stopgap until the `tracing` crate can be added as a dependency: it has levels but no spans or
+//! subscriber, and `capture` only sees lines logged on the calling thread, not the scan workers.
+//! Call sites stick to `debug!`/`info!` with format arguments so they carry over unchanged.
+
+use std::cell::RefCell;
+use std::fmt::Arguments;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Off,
+    Info,  // what was skipped or gave up, in a line
+    Debug, // plus every repo opened, fetch run and error otherwise swallowed
+}
+
+static LEVEL: AtomicU8 = AtomicU8::new(Level::Off as u8);
+
+thread_local! {
+    // tests collect this thread's lines here instead of writing them to stderr
+    static CAPTURED: RefCell<Option<Vec<String>>> = const { RefCell::new(None) };
+}
+
+pub fn set_level(level: Level) {
+    LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// `R_GIT_FU_LOG=info|debug`; anything else leaves logging off
+pub fn env_level() -> Level {
+    match std::env::var("R_GIT_FU_LOG").as_deref() {
+        Ok("debug") => Level::Debug,
+        Ok("info") => Level::Info,
+        _ => Level::Off,
+    }
+}
+
+pub fn enabled(level: Level) -> bool {
+    level as u8 <= LEVEL.load(Ordering::Relaxed) || CAPTURED.with(|captured| captured.borrow().is_some())
+}
+
+pub fn emit(level: Level, message: Arguments) {
+    let label = match level {
+        Level::Debug => "debug",
+        _ => "info",
+    };
+    let line = format!("r-git-fu: {}: {}", label, message);
+    let captured = CAPTURED.with(|captured| match captured.borrow_mut().as_mut() {
+        Some(lines) => {
+            lines.push(line.clone());
+            true
+        }
+        None => false,
+    });
+    if !captured {
+        eprintln!("{}", line);
+    }
+}
+
+/// Every line `f` logs on this thread, at any level
+#[cfg(test)]
+pub fn capture<T>(f: impl FnOnce() -> T) -> (T, Vec<String>) {
+    CAPTURED.with(|captured| *captured.borrow_mut() = Some(Vec::new()));
+    let result = f();
+    let lines = CAPTURED.with(|captured| captured.borrow_mut().take()).unwrap_or_default();
+    (result, lines)
+}
+
+macro_rules! debug {
+    ($($arg:tt)*) => {
+        if $crate::trace::enabled($crate::trace::Level::Debug) {
+            $crate::trace::emit($crate::trace::Level::Debug, format_args!($($arg)*));
+        }
+    };
+}
+
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if $crate::trace::enabled($crate::trace::Level::Info) {
+            $crate::trace::emit($crate::trace::Level::Info, format_args!($($arg)*));
+        }
+    };
+}
+
+pub(crate) use {debug, info};